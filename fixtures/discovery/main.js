@@ -0,0 +1,8 @@
+(self.webpackChunk_twitter_responsive_web=self.webpackChunk_twitter_responsive_web||[]).push([[179],{
+60194:e=>{e.exports={queryId:"xc8f1g7BYqr6VTzTbvNlGw",operationName:"UserByScreenName",operationType:"query",metadata:{featureSwitches:["hidden_profile_likes_enabled","responsive_web_graphql_exclude_directive_enabled","verified_phone_label_enabled","subscriptions_verification_info_enabled","responsive_web_graphql_timeline_navigation_enabled"],fieldToggles:["withAuxiliaryUserLabels"]}}},
+60195:e=>{e.exports={queryId:"PiHWpObvdKQ_9aOhYsrJIw",operationName:"Following",operationType:"query",metadata:{featureSwitches:["rweb_lists_timeline_redesign_enabled","responsive_web_graphql_exclude_directive_enabled","verified_phone_label_enabled","longform_notetweets_consumption_enabled","responsive_web_graphql_timeline_navigation_enabled","responsive_web_media_download_video_enabled"],fieldToggles:[]}}},
+60196:e=>{e.exports={queryId:"3yX7xr2hKjcZYnXt6cU6lQ",operationName:"TweetDetail",operationType:"query",metadata:{featureSwitches:["rweb_lists_timeline_redesign_enabled","responsive_web_graphql_exclude_directive_enabled","verified_phone_label_enabled","longform_notetweets_consumption_enabled","responsive_web_enhance_cards_enabled"],fieldToggles:["withArticleRichContentState"]}}},
+60197:e=>{e.exports={queryId:"zwTrX9CtnMvWlBXjsx95RQ",operationName:"HomeTimeline",operationType:"query",metadata:{featureSwitches:["rweb_lists_timeline_redesign_enabled"],fieldToggles:[]}}},
+60198:e=>{e.exports={queryId:"lZ0GCEojmtQfiUQa5oJSEw",operationName:"CreateTweet",operationType:"mutation",metadata:{featureSwitches:[],fieldToggles:[]}}},
+71003:(e,t,n)=>{"use strict";n.d(t,{Z:()=>o});const o=function(e){return e.operationName}}
+}]);
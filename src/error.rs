@@ -7,14 +7,14 @@ pub enum TwtScrapeError {
     IdParseError(String),
     #[error("Request Failed: {0:?}")]
     RequestFailed(reqwest::Error),
-    #[error("Error Request Status: {0:?}")]
-    ErrRequestStatus(reqwest::Error),
+    #[error("Error Request Status: {0} for {1}")]
+    ErrRequestStatus(u16, String),
     #[error("Failed to load JSON: {0:?}")]
     LoadJsonFailed(reqwest::Error),
     #[error("Failed to access schema")]
     SchemaAccessErr,
     #[error("Schema Error: {0:?}")]
-    SchemaErr(reqwest::Error),
+    SchemaErr(serde_json::Error),
     #[error("ID Parse Error: {0:?}")]
     InvalidProxy(reqwest::Error),
     #[error("Failed to build client: {0:?}")]
@@ -29,10 +29,24 @@ pub enum TwtScrapeError {
     TwitterBadTimeParse(String),
     #[error("The User's JSON as returned by Twitter was not AvailableUser.")]
     UserResultError,
+    #[error("User Unavailable: {0}")]
+    UserUnavailable(String),
     #[error("This Timeline Entry `{0}` Not Supported")]
     BadTimelineEntry(String),
     #[error("Schema Error for {0}: {1}")]
-    BadJSONSchema(&'static str, String)
+    BadJSONSchema(&'static str, String),
+    #[error("No fixture response for {0}")]
+    MissingFixture(String),
+    #[error("Cassette Error: {0}")]
+    CassetteError(String),
+    #[error("Session Error: {0}")]
+    SessionError(String),
+    #[error("Endpoint Discovery Error: {0}")]
+    DiscoveryError(String),
+    #[error("Search Query Error: {0}")]
+    SearchQueryError(String),
+    #[error("Snapshots are of different users: {0} and {1}")]
+    SnapshotMismatch(u64, u64),
 }
 
 impl From<ParseIntError> for TwtScrapeError {
@@ -1,41 +1,34 @@
 use crate::error::SResult;
+use crate::scrape::endpoints::Operation;
+use crate::scrape::pagination::{
+    collect_partial, items, paginate, Checkpoint, ItemError, Page, PartialResult,
+};
 use crate::scrape::Scraper;
 use crate::tweet::{Cursor, FilterCursorTweetRequest, TimelineTerminateTimeline, UserResults};
-use crate::user::{Error, User};
-use crate::usertweets::TimelineAddEntry;
-use crate::TwitterIdType;
+use crate::user::{Error, User, UserLookup};
+use crate::{FilterJSON, TwitterIdType};
+use futures::{Stream, TryStreamExt};
 use rkyv::Archive;
-use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
-use std::fmt::Display;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::json;
 use tracing::warn;
 
 #[cfg(feature = "scrape")]
 pub fn twitter_following_request(
-    id: impl TwitterIdType + Display,
+    scraper: &Scraper,
+    id: impl TwitterIdType,
     following: FollowType,
     cursor: Option<impl AsRef<str>>,
 ) -> String {
-    match following {
-        FollowType::Following => match cursor {
-            Some(cursor) => {
-                let crsr = urlencoding::encode(cursor);
-                format!("https://twitter.com/i/api/graphql/9rGM7YNDYuiqd0Cb0ZwLJw/Following?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A20%2C%22cursor%22%3A%22{crsr}%22%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-            }
-            None => {
-                format!("https://twitter.com/i/api/graphql/9rGM7YNDYuiqd0Cb0ZwLJw/Following?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A20%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-            }
-        },
-        FollowType::Followers => match cursor {
-            Some(cursor) => {
-                let crsr = urlencoding::encode(cursor);
-                format!("https://twitter.com/i/api/graphql/_gXC5CopoM8fIgawvyGpIg/Followers?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A20%2C%22cursor%22%3A%22{crsr}%22%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-            }
-            None => {
-                format!("https://twitter.com/i/api/graphql/_gXC5CopoM8fIgawvyGpIg/Followers?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A20%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-            }
-        },
+    let operation = match following {
+        FollowType::Following => Operation::Following,
+        FollowType::Followers => Operation::Followers,
+    };
+    let mut variables = json!({ "userId": id.to_string() });
+    if let Some(cursor) = cursor {
+        variables["cursor"] = cursor.as_ref().into();
     }
+    scraper.graphql_url(operation, variables)
 }
 
 #[derive(
@@ -53,60 +46,124 @@ pub fn twitter_following_request(
 pub struct Follows {
     pub ftype: FollowType,
     pub data: Vec<User>,
+    // suspended, deactivated and other accounts in the list with no profile
+    #[serde(default)]
+    pub unavailable: Vec<UserLookup>,
 }
 
 #[cfg(feature = "scrape")]
 impl Follows {
+    // one page of users at a time, as twitter hands them out
+    pub fn stream_pages(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+    ) -> impl Stream<Item = SResult<Page<User>>> + '_ {
+        Self::stream_pages_from(scraper, id, ftype, Checkpoint::default())
+    }
+
+    // accounts with no profile become item errors, see `stream_lookup_pages_from`
+    pub fn stream_pages_from(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<User>>> + '_ {
+        Self::stream_entry_pages_from(scraper, id, ftype, start).map_ok(profiles_only)
+    }
+
+    pub fn stream_lookup_pages_from(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<UserLookup>>> + '_ {
+        Self::stream_entry_pages_from(scraper, id, ftype, start).map_ok(lookups_only)
+    }
+
+    // every lookup with the id of the timeline entry it came from
+    fn stream_entry_pages_from(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<(String, UserLookup)>>> + '_ {
+        paginate(start, move |cursor| {
+            FollowReq::fetch_page(scraper, id, ftype, cursor)
+        })
+    }
+
+    pub fn stream(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+    ) -> impl Stream<Item = SResult<User>> + '_ {
+        items(Self::stream_pages(scraper, id, ftype))
+    }
+
     #[tracing::instrument]
     pub async fn get_user_follow(scraper: &Scraper, id: u64, ftype: FollowType) -> SResult<Self> {
-        let mut follow_page_requests = Vec::with_capacity(50);
-
-        let first_request = scraper
-            .api_req::<FollowReq>(scraper.make_get_req(twitter_following_request(id, ftype, None)))
-            .await?;
-        // find the cursor
-        let first_cursor = first_request.filter_cursor(FilterCursorTweetRequest::Bottom);
+        Self::get_user_follow_partial(scraper, id, ftype, Checkpoint::default())
+            .await
+            .into_result()
+    }
 
-        if let Some(fc) = first_cursor {
-            follow_page_requests
-                .append(&mut FollowReq::scroll(scraper, id, ftype, fc).await?.into());
-        }
+    #[tracing::instrument]
+    pub async fn get_user_follow_partial(
+        scraper: &Scraper,
+        id: u64,
+        ftype: FollowType,
+        start: Checkpoint,
+    ) -> PartialResult<Self> {
+        let pages = Self::stream_lookup_pages_from(scraper, id, ftype, start.clone());
+        collect_partial(pages, start).await.map(|lookups| {
+            let (users, unavailable) = lookups
+                .into_iter()
+                .partition::<Vec<_>, _>(|lookup| lookup.user().is_some());
+            Self {
+                ftype,
+                data: users
+                    .into_iter()
+                    .filter_map(|lookup| lookup.into_user().ok())
+                    .collect(),
+                unavailable,
+            }
+        })
+    }
+}
 
-        let mut users = Vec::with_capacity(1000);
-
-        for req in follow_page_requests {
-            if let Rslt::User(tl) = req.data.result {
-                for inst in tl.timeline.instructions {
-                    if let Instruction::TimelineAddEntries(tl_add) = inst {
-                        for entry in tl_add.entries {
-                            if let Entry::User(usr) = entry {
-                                match User::from_result(
-                                    scraper,
-                                    usr.content.item_content.result.result,
-                                )
-                                .await
-                                {
-                                    Ok(us) => {
-                                        users.push(us);
-                                    }
-                                    Err(why) => {
-                                        warn!(
-                                            error = why,
-                                            user_id = id,
-                                            "Failed to get data. Skipping..."
-                                        )
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+#[cfg(feature = "scrape")]
+fn profiles_only(page: Page<(String, UserLookup)>) -> Page<User> {
+    let mut errors = page.errors;
+    let items = page
+        .items
+        .into_iter()
+        .filter_map(|(entry_id, lookup)| match lookup.into_user() {
+            Ok(user) => Some(user),
+            Err(why) => {
+                errors.push(ItemError::new(entry_id, &why));
+                None
             }
-        }
+        })
+        .collect();
 
-        users.shrink_to_fit();
+    Page {
+        items,
+        next_cursor: page.next_cursor,
+        checkpoint: page.checkpoint,
+        errors,
+    }
+}
+
+#[cfg(feature = "scrape")]
+fn lookups_only(page: Page<(String, UserLookup)>) -> Page<UserLookup> {
+    let items = page.items.into_iter().map(|(_, lookup)| lookup).collect();
 
-        Ok(Self { ftype, data: users })
+    Page {
+        items,
+        next_cursor: page.next_cursor,
+        checkpoint: page.checkpoint,
+        errors: page.errors,
     }
 }
 
@@ -114,7 +171,9 @@ impl Follows {
     Copy,
     Clone,
     Debug,
+    Hash,
     PartialEq,
+    Eq,
     PartialOrd,
     Serialize,
     Deserialize,
@@ -139,7 +198,7 @@ pub enum FollowType {
     rkyv::Serialize,
     rkyv::Deserialize,
 )]
-pub struct FollowReq {
+pub(crate) struct FollowReq {
     pub errors: Vec<Error>,
     pub data: Data,
 }
@@ -147,23 +206,22 @@ pub struct FollowReq {
 #[cfg(feature = "scrape")]
 impl FollowReq {
     pub(crate) fn filter_cursor(&self, filter: FilterCursorTweetRequest) -> Option<&str> {
-        if let Rslt::User(tl) = &self.data.result {
-            for inst in &tl.timeline.instructions {
-                if let Instruction::TimelineAddEntries(tl_add) = inst {
-                    for entry in &tl_add.entries {
-                        if let Entry::Cursor(crsr) = entry {
-                            match filter {
-                                FilterCursorTweetRequest::Top => {
-                                    if crsr.entry_id.starts_with("cursor-top") {
-                                        return Some(crsr.content.item_content.value.as_str());
-                                    }
+        let Rslt::User(tl) = &self.data.result;
+        for inst in &tl.timeline.instructions {
+            if let Instruction::AddEntries(tl_add) = inst {
+                for entry in &tl_add.entries {
+                    if let Entry::Cursor(crsr) = entry {
+                        match filter {
+                            FilterCursorTweetRequest::Top => {
+                                if crsr.entry_id.starts_with("cursor-top") {
+                                    return Some(crsr.content.item_content.value.as_str());
                                 }
-                                FilterCursorTweetRequest::Bottom => {
-                                    if crsr.entry_id.starts_with("cursor-bottom")
-                                        || crsr.entry_id.starts_with("cursor-showmorethreads")
-                                    {
-                                        return Some(crsr.content.item_content.value.as_str());
-                                    }
+                            }
+                            FilterCursorTweetRequest::Bottom => {
+                                if crsr.entry_id.starts_with("cursor-bottom")
+                                    || crsr.entry_id.starts_with("cursor-showmorethreads")
+                                {
+                                    return Some(crsr.content.item_content.value.as_str());
                                 }
                             }
                         }
@@ -176,41 +234,52 @@ impl FollowReq {
     }
 
     #[tracing::instrument]
-    pub(crate) async fn scroll(
+    pub(crate) async fn fetch_page(
         scraper: &Scraper,
         id: u64,
         ftype: FollowType,
-        first_cursor: &str,
-    ) -> SResult<VecDeque<Self>> {
-        let mut requests = VecDeque::with_capacity(5);
-
-        let mut cursor_counter = first_cursor.to_string();
-        let mut break_on_next = false;
-        loop {
-            let scrolled_up_request = scraper
-                .api_req::<FollowReq>(scraper.make_get_req(twitter_following_request(
-                    id,
-                    ftype,
-                    Some(&cursor_counter),
-                )))
-                .await?;
-
-            scrolled_up_request.json_request_filter_errors()?;
-
-            requests.push_front(scrolled_up_request);
-            if break_on_next {
-                break;
-            }
+        cursor: Option<String>,
+    ) -> SResult<Page<(String, UserLookup)>> {
+        let request = scraper
+            .api_req::<FollowReq>(
+                scraper.make_get_req(twitter_following_request(scraper, id, ftype, cursor)),
+            )
+            .await?;
+
+        request.filter_json_err()?;
 
-            match scrolled_up_request.filter_cursor(FilterCursorTweetRequest::Bottom) {
-                Some(bottom) => {
-                    cursor_counter = bottom.to_string();
+        let next_cursor = request
+            .filter_cursor(FilterCursorTweetRequest::Bottom)
+            .map(str::to_string);
+        let mut users = Vec::with_capacity(20);
+        let mut errors = vec![];
+
+        let Rslt::User(tl) = request.data.result;
+        for inst in tl.timeline.instructions {
+            if let Instruction::AddEntries(tl_add) = inst {
+                for entry in tl_add.entries {
+                    if let Entry::User(usr) = entry {
+                        let result = usr.content.item_content.result.result;
+                        let item = result.rest_id().unwrap_or(&usr.entry_id).to_string();
+                        match UserLookup::from_result(scraper, result).await {
+                            Ok(us) => {
+                                users.push((usr.entry_id, us));
+                            }
+                            Err(why) => {
+                                warn!(
+                                    error = %why,
+                                    user_id = id,
+                                    "Failed to get data. Skipping..."
+                                );
+                                errors.push(ItemError::new(item, &why));
+                            }
+                        }
+                    }
                 }
-                None => break_on_next = true,
             }
         }
 
-        Ok(requests)
+        Ok(Page::new(users, next_cursor).with_errors(errors))
     }
 }
 
@@ -247,7 +316,6 @@ pub(crate) struct Data {
 )]
 #[serde(tag = "__typename")]
 pub(crate) enum Rslt {
-    #[serde(flatten)]
     User(Timeline),
 }
 
@@ -297,11 +365,12 @@ pub(crate) struct InnerTimeline {
 )]
 #[serde(tag = "type")]
 pub(crate) enum Instruction {
-    TimelineClearCache,
-    #[serde(flatten)]
-    TimelineTerminateTimeline(TimelineTerminateTimeline),
-    #[serde(flatten)]
-    TimelineAddEntries(TimelineAddEntry),
+    #[serde(rename = "TimelineClearCache")]
+    ClearCache,
+    #[serde(rename = "TimelineTerminateTimeline")]
+    TerminateTimeline(TimelineTerminateTimeline),
+    #[serde(rename = "TimelineAddEntries")]
+    AddEntries(TimelineAddEntries),
 }
 
 #[derive(
@@ -321,22 +390,36 @@ pub(crate) struct TimelineAddEntries {
 }
 
 #[derive(
-    Clone,
-    Debug,
-    Hash,
-    PartialEq,
-    Eq,
-    Serialize,
-    Deserialize,
-    Archive,
-    rkyv::Serialize,
-    rkyv::Deserialize,
+    Clone, Debug, Hash, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize,
 )]
 pub(crate) enum Entry {
     User(Usr),
     Cursor(Cursor),
 }
 
+// users and cursors are only told apart by the entry id
+impl<'de> Deserialize<'de> for Entry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entry = serde_json::Value::deserialize(deserializer)?;
+        let entry_id = entry
+            .get("entryId")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| de::Error::missing_field("entryId"))?;
+
+        if entry_id.starts_with("user-") {
+            serde_json::from_value(entry).map(Entry::User)
+        } else if entry_id.starts_with("cursor-") {
+            serde_json::from_value(entry).map(Entry::Cursor)
+        } else {
+            return Err(de::Error::unknown_variant(entry_id, &["user", "cursor"]));
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -350,6 +433,8 @@ pub(crate) enum Entry {
     rkyv::Deserialize,
 )]
 pub(crate) struct Usr {
+    #[serde(rename = "entryId")]
+    pub entry_id: String,
     pub content: Content,
 }
 
@@ -385,3 +470,46 @@ pub(crate) struct Content {
 pub(crate) struct ItemContent {
     pub result: UserResults,
 }
+
+#[cfg(feature = "scrape")]
+#[test]
+fn keep_item_errors_on_follow_pages() {
+    use crate::error::TwtScrapeError::BadJSONSchema;
+    use crate::user::Unavailability;
+
+    let suspended = Unavailability {
+        reason: "Suspended".to_string(),
+        message: "This account is suspended".to_string(),
+    };
+    let page = || {
+        Page::new(
+            vec![(
+                "user-404".to_string(),
+                UserLookup::Suspended(suspended.clone()),
+            )],
+            Some("bottom-1".to_string()),
+        )
+        .with_errors(vec![ItemError::new(
+            "user-405",
+            &BadJSONSchema("User", "No legacy".to_string()),
+        )])
+    };
+
+    let lookups = lookups_only(page());
+    assert_eq!(
+        lookups.items,
+        vec![UserLookup::Suspended(suspended.clone())]
+    );
+    assert_eq!(lookups.next_cursor.as_deref(), Some("bottom-1"));
+    assert_eq!(lookups.errors, page().errors);
+
+    // profiles only, so the suspended account joins the page's errors
+    let profiles = profiles_only(page());
+    assert!(profiles.items.is_empty());
+    let failed = profiles
+        .errors
+        .iter()
+        .map(|error| error.item.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(failed, vec!["user-405", "user-404"]);
+}
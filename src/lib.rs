@@ -7,6 +7,7 @@ pub mod moderated_tweets;
 #[cfg(feature = "scrape")]
 pub mod scrape;
 pub mod search;
+pub mod snapshot;
 pub mod timeline;
 pub mod tweet;
 pub mod user;
@@ -1,29 +1,29 @@
 use crate::error::SResult;
-use crate::error::TwtScrapeError::TwitterJSONError;
+use crate::scrape::endpoints::Operation;
+use crate::scrape::pagination::{
+    collect_partial, items, paginate, Checkpoint, ItemError, Page, PartialResult,
+};
 use crate::scrape::Scraper;
-use crate::tweet::{Entry, FilterCursorTweetRequest, Instruction, Tweet};
+use crate::tweet::{EntryVariant, FilterCursorTweetRequest, Instruction, Tweet};
 use crate::user::Error;
 use crate::{FilterJSON, TwitterIdType};
+use futures::Stream;
 use rkyv::Archive;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
-use std::fmt::Display;
+use serde_json::json;
 use tracing::{instrument, warn};
 
 #[cfg(feature = "scrape")]
 pub fn twitter_moderated_req(
-    tweet_id: impl TwitterIdType + Display,
+    scraper: &Scraper,
+    tweet_id: impl TwitterIdType,
     cursor: Option<impl AsRef<str>>,
 ) -> String {
-    match cursor {
-        Some(cursor) => {
-            let crsr = urlencoding::encode(cursor.as_ref());
-            format!("https://twitter.com/i/api/graphql/c9IdrvgCZw7oxPZFPBpyrg/ModeratedTimeline?variables=%7B%22rootTweetId%22%3A%22{tweet_id}%22%2C%22cursor%22%3A%22{crsr}%22%2C%22count%22%3A20%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D%26features%3D%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
-        None => {
-            format!("https://twitter.com/i/api/graphql/c9IdrvgCZw7oxPZFPBpyrg/ModeratedTimeline?variables=%7B%22rootTweetId%22%3A%22{tweet_id}%22%2C%22count%22%3A20%2C%22includePromotedContent%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
+    let mut variables = json!({ "rootTweetId": tweet_id.to_string() });
+    if let Some(cursor) = cursor {
+        variables["cursor"] = cursor.as_ref().into();
     }
+    scraper.graphql_url(Operation::ModeratedTimeline, variables)
 }
 
 #[derive(
@@ -45,53 +45,47 @@ pub struct ModeratedTweets {
 
 #[cfg(feature = "scrape")]
 impl ModeratedTweets {
-    #[instrument]
-    pub async fn moderated_tweets(scraper: &Scraper, tweet_id: u64) -> SResult<Self> {
-        let first_request = scraper
-            .api_req::<ModTweetsReq>(scraper.make_get_req(twitter_moderated_req(tweet_id, None)))
-            .await?;
-
-        first_request.filter_json_err()?;
+    pub fn stream_pages(
+        scraper: &Scraper,
+        tweet_id: u64,
+    ) -> impl Stream<Item = SResult<Page<Tweet>>> + '_ {
+        Self::stream_pages_from(scraper, tweet_id, Checkpoint::default())
+    }
 
-        let mut moderated_reqs = Vec::with_capacity(5);
+    pub fn stream_pages_from(
+        scraper: &Scraper,
+        tweet_id: u64,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<Tweet>>> + '_ {
+        paginate(start, move |cursor| {
+            ModTweetsReq::fetch_page(scraper, tweet_id, cursor)
+        })
+    }
 
-        let first_cursor = first_request.filter_cursor(FilterCursorTweetRequest::Bottom);
+    pub fn stream(scraper: &Scraper, tweet_id: u64) -> impl Stream<Item = SResult<Tweet>> + '_ {
+        items(Self::stream_pages(scraper, tweet_id))
+    }
 
-        if let Some(cursor) = first_cursor {
-            moderated_reqs.append(
-                &mut ModTweetsReq::scroll(
-                    scraper,
-                    tweet_id,
-                    cursor.to_string(),
-                    FilterCursorTweetRequest::Bottom,
-                )
-                .await?
-                .into(),
-            );
-        }
+    #[instrument]
+    pub async fn moderated_tweets(scraper: &Scraper, tweet_id: u64) -> SResult<Self> {
+        Self::moderated_tweets_partial(scraper, tweet_id, Checkpoint::default())
+            .await
+            .into_result()
+    }
 
-        let mut tweets = Vec::with_capacity(moderated_reqs.len() * 5);
-
-        for modtwt in moderated_reqs {
-            if let Rslt::TimelineResponse(tlr) = modtwt.data.tweet.result {
-                for inst in tlr.instructions {
-                    if let Instruction::TimelineAddEntries(entry) = inst {
-                        for entry in entry.entries {
-                            if let Entry::Tweet(twtent) = entry {
-                                let twet =
-                                    Tweet::new_from_entry(&twtent.item_content.tweet_results)?;
-                                tweets.push(twet);
-                            }
-                        }
-                    }
-                }
+    #[instrument]
+    pub async fn moderated_tweets_partial(
+        scraper: &Scraper,
+        tweet_id: u64,
+        start: Checkpoint,
+    ) -> PartialResult<Self> {
+        let pages = Self::stream_pages_from(scraper, tweet_id, start.clone());
+        collect_partial(pages, start).await.map(|mut tweets| {
+            tweets.shrink_to_fit();
+            ModeratedTweets {
+                of_tweet: tweet_id,
+                tweets,
             }
-        }
-
-        tweets.shrink_to_fit();
-        Ok(ModeratedTweets {
-            of_tweet: tweet_id,
-            tweets,
         })
     }
 }
@@ -116,51 +110,53 @@ pub(crate) struct ModTweetsReq {
 #[cfg(feature = "scrape")]
 impl ModTweetsReq {
     pub(crate) fn filter_cursor(&self, filter: FilterCursorTweetRequest) -> Option<&str> {
-        if let Rslt::TimelineResponse(tlr) = &self.data.tweet.result {
-            for inst in tlr.instructions {
-                inst.filter_cursor(filter)
-            }
-        }
-
-        None
+        let Rslt::TimelineResponse(tlr) = &self.data.tweet.result;
+        tlr.instructions
+            .iter()
+            .find_map(|inst| inst.filter_cursor(filter))
     }
 
+    // an error on any page fails it, the pages before it are kept with a checkpoint to resume from
     #[tracing::instrument]
-    pub(crate) async fn scroll(
+    pub(crate) async fn fetch_page(
         scraper: &Scraper,
-        id: impl TwitterIdType + Display,
-        first_cursor: String,
-        filter: FilterCursorTweetRequest,
-    ) -> SResult<VecDeque<Self>> {
-        let mut requests = VecDeque::with_capacity(5);
-
-        let mut cursor_counter = first_cursor.to_string();
-        let mut break_on_next = false;
-        loop {
-            let scrolled_up_request = scraper
-                .api_req::<ModTweetsReq>(
-                    scraper.make_get_req(twitter_moderated_req(&id, Some(&cursor_counter))),
-                )
-                .await?;
-
-            if let Err(why) = scrolled_up_request.json_request_filter_errors() {
-                warn!(error = why, "Error while scrolling, ending early.")
-            }
-
-            requests.push_front(scrolled_up_request);
-            if break_on_next {
-                break;
-            }
+        tweet_id: u64,
+        cursor: Option<String>,
+    ) -> SResult<Page<Tweet>> {
+        let request = scraper
+            .api_req::<ModTweetsReq>(
+                scraper.make_get_req(twitter_moderated_req(scraper, tweet_id, cursor)),
+            )
+            .await?;
 
-            match scrolled_up_request.filter_cursor(filter) {
-                Some(up) => {
-                    cursor_counter = up.to_string();
+        request.filter_json_err()?;
+
+        let next_cursor = request
+            .filter_cursor(FilterCursorTweetRequest::Bottom)
+            .map(str::to_string);
+        let mut tweets = Vec::with_capacity(20);
+        let mut errors = vec![];
+
+        let Rslt::TimelineResponse(tlr) = request.data.tweet.result;
+        for inst in tlr.instructions {
+            if let Instruction::TimelineAddEntries(entry) = inst {
+                for entry in entry.entries {
+                    if let EntryVariant::Tweet(twtent) = &entry.content {
+                        let results = &twtent.item_content.tweet_results;
+                        match Tweet::new_from_entry(results) {
+                            Ok(tweet) => tweets.push(tweet),
+                            Err(why) => {
+                                warn!(error = %why, tweet_id, "Failed to parse tweet. Skipping...");
+                                let item = results.rest_id().unwrap_or(&entry.entry_id);
+                                errors.push(ItemError::new(item, &why));
+                            }
+                        }
+                    }
                 }
-                None => break_on_next = true,
             }
         }
 
-        Ok(requests)
+        Ok(Page::new(tweets, next_cursor).with_errors(errors))
     }
 }
 
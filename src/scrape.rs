@@ -1,47 +1,102 @@
 use ahash::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use reqwest::cookie::{CookieStore, Jar};
-use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode};
+use reqwest::{Client, Proxy, Url};
 
+pub mod cassette;
+pub mod discovery;
+pub mod endpoints;
+pub mod pagination;
+pub mod proxy;
+pub mod rate_limit;
+pub mod retry;
+pub mod session;
 mod timing;
-use crate::error::TwtScrapeError::{ErrRequestStatus, InvalidProxy, RequestFailed};
+pub mod transport;
+use crate::error::TwtScrapeError::InvalidProxy;
 use crate::error::{SResult, TwtScrapeError};
+use cassette::{RecordingTransport, ReplayTransport};
+use endpoints::{EndpointConfig, Endpoints, Operation};
+use proxy::{ProxyPool, ProxyStats};
+use rate_limit::{endpoint_of, RateLimit, RateLimits};
+use retry::RetryPolicy;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use session::{parse_cookie_header, SavedGuestToken, SessionState};
+pub use timing::GuestTokenStats;
 use timing::*;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
 use tracing::warn;
+use transport::{ApiRequest, ApiResponse, ReqwestTransport, Transport};
 
-//Intended to be associated with a single task
-//Making the time pieces internally mutable with a mutex could make this shareable
-#[derive(Debug)]
+const TWITTER_URL: &str = "https://twitter.com";
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionMode {
+    Guest,
+    Authenticated,
+}
+
+// How a profile's t.co website link becomes the real url. Twitter usually sends the
+// expanded url along, so only Eager costs a request for every user built.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebsiteResolution {
+    // keep whatever twitter sent
+    Never,
+    // follow every link's redirects while building the user
+    Eager,
+    // keep whatever twitter sent, `User::resolve_website` follows it on demand
+    #[default]
+    Lazy,
+}
+
+// the `auth_token` and `ct0` cookies of a logged in account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthSession {
+    pub auth_token: String,
+    pub csrf_token: String,
+}
+
+// Cheap to clone and safe to share between tasks.
+// Every clone uses the same guest token, delay and cookies, so a whole process
+// fans out over one rate limited identity.
+// Guest token slot `n` always goes through proxy `n % proxies`.
+#[derive(Debug, Clone)]
 pub struct Scraper {
-    bearer_token: String,
-    client: Client,
-    delayer: Delayer,
-    guest_token: TimedToken,
+    bearer_token: Arc<str>,
+    proxies: Arc<ProxyPool>,
+    endpoints: Arc<RwLock<Endpoints>>,
+    delayer: Arc<Delayer>,
+    auth: Option<Arc<AuthSession>>,
+    guest_token: Arc<TokenPool>,
+    rate_limits: Arc<RateLimits>,
+    retry: Arc<RetryPolicy>,
     cookie: Arc<Jar>,
+    in_flight: Option<Arc<Semaphore>>,
+    website_resolution: WebsiteResolution,
 }
 
 impl Scraper {
     // equivalent to api.GetGuestToken
     #[tracing::instrument]
-    async fn refresh_token(&self) -> SResult<String> {
-        let successful_response = self
-            .client
-            .post("https://api.twitter.com/1.1/guest/activate.json")
-            .header("Authorization", format!("Bearer {}", self.bearer_token))
-            .send()
-            .await
-            .map_err(RequestFailed)?
-            .error_for_status()
-            .map_err(ErrRequestStatus)?;
+    async fn refresh_token(&self, route: usize) -> SResult<String> {
+        let response = self
+            .proxies
+            .transport(route)
+            .execute(
+                ApiRequest::post("https://api.twitter.com/1.1/guest/activate.json")
+                    .bearer_auth(&self.bearer_token),
+            )
+            .await;
+        self.proxies.report(route, &response);
+        let successful_response = response?.error_for_status()?;
 
-        let mut data: HashMap<String, Value> = successful_response
-            .json()
-            .await
-            .map_err(TwtScrapeError::LoadJsonFailed)?;
+        let mut data: HashMap<String, Value> = successful_response.json()?;
 
         match data.remove("guest_token") {
             Some(Value::String(s)) => Ok(s),
@@ -49,69 +104,509 @@ impl Scraper {
         }
     }
 
-    pub fn make_get_req(&self, url: impl AsRef<str>) -> RequestBuilder {
-        self.client.get(url.as_ref())
+    pub fn make_get_req(&self, url: impl AsRef<str>) -> ApiRequest {
+        ApiRequest::get(url.as_ref())
+    }
+
+    // `variables` is a json object with the call's ids and cursor
+    pub fn graphql_url(&self, operation: Operation, variables: Value) -> String {
+        self.endpoints.read().unwrap().url(operation, variables)
+    }
+
+    pub fn website_resolution(&self) -> WebsiteResolution {
+        self.website_resolution
+    }
+
+    pub fn endpoints(&self) -> Endpoints {
+        self.endpoints.read().unwrap().clone()
+    }
+
+    // swaps in new query ids or features for every clone of the scraper
+    pub fn update_endpoints(&self, config: &EndpointConfig) {
+        self.endpoints.write().unwrap().apply(config);
+    }
+
+    // looks up the current query ids in the web client, see `discovery`
+    pub async fn refresh_endpoints(&self) -> SResult<EndpointConfig> {
+        let discovered = discovery::discover_endpoints(self).await?;
+        self.update_endpoints(&discovered);
+        Ok(discovered)
+    }
+
+    // a plain page load, no api headers or guest token
+    pub(crate) async fn fetch_page(&self, url: &str) -> SResult<ApiResponse> {
+        self.delayer.wait().await;
+        let route = self.proxies.next_route();
+        let response = self
+            .proxies
+            .transport(route)
+            .execute(ApiRequest::get(url))
+            .await;
+        self.proxies.report(route, &response);
+        response?.error_for_status()
     }
 
     #[tracing::instrument]
-    pub async fn api_req<T: DeserializeOwned>(&self, request: RequestBuilder) -> SResult<T> {
+    pub async fn api_req<T: DeserializeOwned>(&self, request: ApiRequest) -> SResult<T> {
         let response = self.api_req_raw_request(request).await?;
-        response.json().await.map_err(TwtScrapeError::SchemaErr)
+        response.json()
     }
 
     #[tracing::instrument]
-    pub async fn api_req_raw_request(&self, request: RequestBuilder) -> SResult<Response> {
+    pub async fn api_req_raw_request(&self, request: ApiRequest) -> SResult<ApiResponse> {
+        // the semaphore is never closed
+        let _permit = match &self.in_flight {
+            Some(in_flight) => Some(in_flight.acquire().await.unwrap()),
+            None => None,
+        };
         self.delayer.wait().await;
-        let token = self.guest_token.get_token(self.refresh_token()).await?;
-        let headed = request
-            .header("X-Guest-Token", token)
-            .bearer_auth(&self.bearer_token);
 
-        match headed
-            .send()
-            .await
-            .map_err(RequestFailed)?
-            .error_for_status()
-        {
-            Ok(req) => Ok(req),
-            Err(why) => {
-                warn!(error = why, "Got an error while asking twitter. Retrying.");
-                // twitter randomly tells us to fuck off
-                // retrying after a wait usually works
-                let mut std_delay_secs = 1;
-                if let Some(sc) = why.status() {
-                    if sc == StatusCode::TOO_MANY_REQUESTS {
-                        std_delay_secs = 5;
+        let endpoint = endpoint_of(&request.url);
+        let mut attempt = 1;
+        loop {
+            // Each guest token has its own budget and is parked when it runs out, a logged in
+            // session has the one. Checked before every try, a 429 may have just used it up.
+            if self.auth.is_some() {
+                if let Some(wait) = self.rate_limits.wait_time(&endpoint) {
+                    warn!(
+                        endpoint,
+                        ?wait,
+                        "Rate limit used up, waiting for the reset."
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+            }
+
+            let (why, invalidated) = match self.send_with_session(&request).await {
+                Ok(response) => {
+                    let invalidated = self.auth.is_none() && token_invalidated(&response);
+                    match response.error_for_status() {
+                        Ok(response) => return Ok(response),
+                        Err(why) => (why, invalidated),
                     }
                 }
-                tokio::time::sleep(Duration::from_secs(std_delay_secs)).await;
-
-                let token = self.guest_token.get_token(self.refresh_token()).await?;
-                let headed = request
-                    .header("Authorization", format!("Bearer {}", self.bearer_token))
-                    .header("X-Guest-Token", token);
-
-                Ok(headed
-                    .send()
-                    .await
-                    .map_err(RequestFailed)?
-                    .error_for_status()
-                    .map_err(ErrRequestStatus)?)
+                Err(why) => (why, false),
+            };
+
+            // an invalidated token was already thrown out of the pool, the retry gets a fresh one
+            if attempt >= self.retry.max_attempts || !(invalidated || self.retry.is_retryable(&why))
+            {
+                return Err(why);
+            }
+
+            let wait = self.retry.delay(attempt - 1);
+            warn!(error = %why, attempt, ?wait, "Got an error while asking twitter. Retrying.");
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+
+    pub async fn guest_token_stats(&self) -> Vec<GuestTokenStats> {
+        self.guest_token.stats().await
+    }
+
+    pub fn proxy_stats(&self) -> Vec<ProxyStats> {
+        self.proxies.stats()
+    }
+
+    // Hits twitter.com through every proxy, quarantined or not.
+    // Working ones are let back in, failing ones count towards quarantine.
+    #[tracing::instrument]
+    pub async fn check_proxies(&self) -> Vec<ProxyStats> {
+        for route in 0..self.proxies.len() {
+            let response = self
+                .proxies
+                .transport(route)
+                .execute(ApiRequest::get(TWITTER_URL))
+                .await;
+            self.proxies.report(route, &response);
+        }
+        self.proxies.stats()
+    }
+
+    fn route_of(&self, slot: usize) -> usize {
+        slot % self.proxies.len()
+    }
+
+    // the last budget twitter reported for an endpoint, see `rate_limit::endpoint_of`
+    pub fn rate_limit(&self, endpoint: &str) -> Option<RateLimit> {
+        self.rate_limits.get(endpoint)
+    }
+
+    pub fn rate_limits(&self) -> HashMap<String, RateLimit> {
+        self.rate_limits.all()
+    }
+
+    pub fn session_mode(&self) -> SessionMode {
+        match self.auth {
+            Some(_) => SessionMode::Authenticated,
+            None => SessionMode::Guest,
+        }
+    }
+
+    fn twitter_cookies(&self) -> Vec<(String, String)> {
+        TWITTER_URL
+            .parse::<Url>()
+            .ok()
+            .and_then(|url| self.cookie.cookies(&url))
+            .and_then(|header| header.to_str().ok().map(parse_cookie_header))
+            .unwrap_or_default()
+    }
+
+    // twitter rotates ct0, so prefer whatever the cookie jar holds now
+    fn csrf_token(&self, auth: &AuthSession) -> String {
+        self.twitter_cookies()
+            .into_iter()
+            .find(|(name, _)| name == "ct0")
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| auth.csrf_token.clone())
+    }
+
+    pub async fn session_state(&self) -> SessionState {
+        let now = SystemTime::now();
+        let guest_tokens = self
+            .guest_token
+            .live_tokens()
+            .await
+            .into_iter()
+            .map(|(slot, token, age)| SavedGuestToken {
+                token,
+                activated: (now - age)
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                proxy: Some(self.proxies.name(self.route_of(slot)).to_string()),
+            })
+            .collect();
+
+        SessionState {
+            bearer_token: self.bearer_token.to_string(),
+            auth: self.auth.as_deref().cloned(),
+            guest_tokens,
+            cookies: self.twitter_cookies(),
+            rate_limits: self.rate_limits.all(),
+        }
+    }
+
+    pub async fn save_session(&self, path: impl AsRef<Path>) -> SResult<()> {
+        self.session_state().await.save(path).await
+    }
+
+    // Logged in sessions send the csrf header over the next healthy proxy, guest sessions send
+    // the next guest token in the pool over its own proxy, parking it when its budget runs out
+    // or invalidating it if twitter complains.
+    async fn send_with_session(&self, request: &ApiRequest) -> SResult<ApiResponse> {
+        let headed = request.clone().bearer_auth(&self.bearer_token);
+        let (headed, slot, route) = match &self.auth {
+            Some(auth) => (
+                headed
+                    .header("x-csrf-token", self.csrf_token(auth))
+                    .header("x-twitter-auth-type", "OAuth2Session")
+                    .header("x-twitter-active-user", "yes"),
+                None,
+                self.proxies.next_route(),
+            ),
+            None => {
+                let (slot, token) = self
+                    .guest_token
+                    .acquire(
+                        |slot| self.proxies.is_usable(self.route_of(slot)),
+                        |slot| self.refresh_token(self.route_of(slot)),
+                    )
+                    .await?;
+                (
+                    headed.header("X-Guest-Token", token),
+                    Some(slot),
+                    self.route_of(slot),
+                )
+            }
+        };
+
+        let response = self.proxies.transport(route).execute(headed).await;
+        self.proxies.report(route, &response);
+        let response = response?;
+        let limit = RateLimit::from_response(&response);
+        if let Some(limit) = limit {
+            self.rate_limits.update(endpoint_of(&request.url), limit);
+        }
+
+        if let Some(slot) = slot {
+            if response.status == 429 || limit.is_some_and(|limit| limit.is_exhausted()) {
+                self.guest_token
+                    .park(slot, rate_limit_reset(&response))
+                    .await;
+            } else if token_invalidated(&response) {
+                self.guest_token.invalidate(slot).await;
             }
         }
+
+        Ok(response)
+    }
+}
+
+const DEFAULT_RATE_LIMIT_PARK: Duration = Duration::from_secs(5);
+
+fn rate_limit_reset(response: &ApiResponse) -> Instant {
+    let wait = RateLimit::from_response(response)
+        .and_then(|limit| limit.time_until_reset())
+        .unwrap_or(DEFAULT_RATE_LIMIT_PARK);
+    Instant::now() + wait
+}
+
+// 401, or 403 with code 239 "Bad guest token"
+fn token_invalidated(response: &ApiResponse) -> bool {
+    match response.status {
+        401 => true,
+        403 => String::from_utf8_lossy(&response.body).contains("\"code\":239"),
+        _ => false,
     }
 }
 
 #[test]
 fn make_scraper() {
-    tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
+    transport::block_on(async {
+        let _scraper = ScraperBuilder::new().finish().await.unwrap();
+        //println!("{:?}", scraper);
+    });
+}
+
+#[test]
+fn make_scraper_offline() {
+    transport::block_on(async {
+        let transport = Arc::new(
+            transport::FixtureTransport::new()
+                .with_json("https://twitter.com/i/api/test", "{\"ok\":true}"),
+        );
+        let scraper = transport::fixture_scraper(&transport).await;
+
+        let data: HashMap<String, bool> = scraper
+            .api_req(scraper.make_get_req("https://twitter.com/i/api/test"))
+            .await
+            .unwrap();
+        assert_eq!(data.get("ok"), Some(&true));
+        assert_eq!(
+            transport.request_count("https://api.twitter.com/1.1/guest/"),
+            1
+        );
+    });
+}
+
+#[test]
+fn retries_only_retryable_statuses() {
+    transport::block_on(async {
+        let flaky = "https://twitter.com/i/api/flaky";
+        let missing = "https://twitter.com/i/api/missing";
+        let transport = transport::FixtureTransport::new()
+            .with_guest_token("1234")
+            .with_response(flaky, ApiResponse::new(flaky, 503, ""))
+            .with_json(flaky, "true")
+            .with_response(missing, ApiResponse::new(missing, 404, ""))
+            .with_json(missing, "true");
+        let scraper = ScraperBuilder::new()
+            .with_transport(Arc::new(transport))
+            .with_retry_policy(RetryPolicy::new().with_base_delay(Duration::ZERO))
+            .finish()
+            .await
+            .unwrap();
+
+        assert!(scraper
+            .api_req::<bool>(scraper.make_get_req(flaky))
+            .await
+            .unwrap());
+        assert!(matches!(
+            scraper.api_req::<bool>(scraper.make_get_req(missing)).await,
+            Err(TwtScrapeError::ErrRequestStatus(404, _))
+        ));
+    });
+}
+
+#[test]
+fn authenticated_session_skips_guest_activation() {
+    transport::block_on(async {
+        // no guest activation fixture, finish would fail if it tried
+        let transport =
+            transport::FixtureTransport::new().with_json("https://twitter.com/i/api/test", "true");
+        let scraper = ScraperBuilder::new()
+            .with_transport(Arc::new(transport))
+            .with_auth_session("auth".to_string(), "csrf".to_string())
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(scraper.session_mode(), SessionMode::Authenticated);
+        assert_eq!(
+            scraper.csrf_token(scraper.auth.as_ref().unwrap()),
+            "csrf".to_string()
+        );
+        assert!(scraper
+            .api_req::<bool>(scraper.make_get_req("https://twitter.com/i/api/test"))
+            .await
+            .unwrap());
+    });
+}
+
+#[test]
+fn restore_saved_session() {
+    transport::block_on(async {
+        let path =
+            std::env::temp_dir().join(format!("twtscrape-session-{}.json", std::process::id()));
+        let first = ScraperBuilder::new()
+            .with_transport(Arc::new(
+                transport::FixtureTransport::new().with_guest_token("saved"),
+            ))
+            .with_cookies(HashMap::from_iter([("lang".to_string(), "en".to_string())]))
+            .finish()
+            .await
+            .unwrap();
+        first.save_session(&path).await.unwrap();
+
+        // a second activation would hand out a different token
+        let second = ScraperBuilder::new()
+            .with_transport(Arc::new(
+                transport::FixtureTransport::new().with_guest_token("fresh"),
+            ))
+            .with_session_file(&path)
+            .finish()
+            .await
+            .unwrap();
+        let state = second.session_state().await;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(state.guest_tokens.len(), 1);
+        assert_eq!(state.guest_tokens[0].token, "saved");
+        assert!(state
+            .cookies
+            .contains(&("lang".to_string(), "en".to_string())));
+    });
+}
+
+#[test]
+fn tokens_stay_on_their_proxy() {
+    transport::block_on(async {
+        let url = "https://twitter.com/i/api/test";
+        let blocked = transport::FixtureTransport::new()
+            .with_guest_token("a")
+            .with_response(url, ApiResponse::new(url, 429, ""));
+        let working = transport::FixtureTransport::new()
+            .with_guest_token("b")
+            .with_json(url, "true");
+        let scraper = ScraperBuilder::new()
+            .with_proxy_transports(vec![
+                ("a".to_string(), Arc::new(blocked)),
+                ("b".to_string(), Arc::new(working)),
+            ])
+            .with_proxy_quarantine(1, Duration::from_secs(60))
+            .with_retry_policy(RetryPolicy::new().with_base_delay(Duration::ZERO))
+            .finish()
+            .await
+            .unwrap();
+
+        let state = scraper.session_state().await;
+        let saved = state
+            .guest_tokens
+            .iter()
+            .map(|saved| (saved.token.as_str(), saved.proxy.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(saved, vec![("a", "a"), ("b", "b")]);
+
+        // the first try lands on proxy a, gets rate limited and quarantines it
+        assert!(scraper
+            .api_req::<bool>(scraper.make_get_req(url))
+            .await
+            .unwrap());
+        let stats = scraper.proxy_stats();
+        assert!(stats[0].quarantined_for.is_some());
+        assert_eq!(stats[1].quarantined_for, None);
+    });
+}
+
+#[cfg(test)]
+fn exhausted_response(url: &str, status: u16, body: &str, reset_in: u64) -> ApiResponse {
+    let reset = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
         .unwrap()
-        .block_on(async {
-            let _scraper = ScraperBuilder::new().finish().await.unwrap();
-            //println!("{:?}", scraper);
-        });
+        .as_secs()
+        + reset_in;
+    ApiResponse::new(url, status, body)
+        .with_header("content-type", "application/json")
+        .with_header("x-rate-limit-limit", "50")
+        .with_header("x-rate-limit-remaining", "0")
+        .with_header("x-rate-limit-reset", reset.to_string())
+}
+
+#[test]
+fn spent_guest_budget_only_parks_its_token() {
+    transport::block_on(async {
+        let url = "https://twitter.com/i/api/graphql/abc/Following";
+        let transport = Arc::new(
+            transport::FixtureTransport::new()
+                .with_response(url, exhausted_response(url, 200, "true", 60))
+                .with_json(url, "true"),
+        );
+        let builder = ScraperBuilder::new().with_guest_tokens(2);
+        let scraper = transport::fixture_scraper_from(builder, &transport).await;
+
+        // the other token still has its budget, nothing waits for the reset
+        let start = Instant::now();
+        for _ in 0..2 {
+            assert!(scraper
+                .api_req::<bool>(scraper.make_get_req(url))
+                .await
+                .unwrap());
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let stats = scraper.guest_token_stats().await;
+        let parked = stats
+            .iter()
+            .filter(|stats| stats.parked_for.is_some())
+            .count();
+        assert_eq!(parked, 1);
+    });
+}
+
+#[test]
+fn rate_limited_retry_waits_for_the_reset() {
+    transport::block_on(async {
+        let url = "https://twitter.com/i/api/graphql/abc/Following";
+        let transport = transport::FixtureTransport::new()
+            .with_response(url, exhausted_response(url, 429, "", 2))
+            .with_json(url, "true");
+        let scraper = ScraperBuilder::new()
+            .with_transport(Arc::new(transport))
+            .with_auth_session("auth".to_string(), "csrf".to_string())
+            .with_retry_policy(RetryPolicy::new().with_base_delay(Duration::ZERO))
+            .finish()
+            .await
+            .unwrap();
+
+        // the 429 says when the budget comes back, the retry waits for that
+        // rather than the policy's zero delay
+        let start = Instant::now();
+        assert!(scraper
+            .api_req::<bool>(scraper.make_get_req(url))
+            .await
+            .unwrap());
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    });
+}
+
+#[test]
+fn scraper_is_shareable() {
+    fn shareable<T: Send + Sync + Clone + 'static>() {}
+    #[allow(dead_code)]
+    fn spawnable<F: std::future::Future + Send + 'static>(_: F) {}
+    fn _requests_are_spawnable(scraper: Scraper) {
+        spawnable(async move { scraper.api_req_raw_request(ApiRequest::get("")).await });
+    }
+
+    shareable::<Scraper>();
 }
 
 #[derive(Debug, Clone)]
@@ -119,10 +614,23 @@ pub struct ScraperBuilder {
     bearer_token: String,
     delay: Option<u64>,
     variation: Option<u64>,
-    proxy: Option<String>,
+    proxies: Vec<String>,
     proxy_auth: Option<(String, String)>,
+    quarantine: Option<(u32, Duration)>,
     user_agent: Option<String>,
     cookie: Option<Arc<Jar>>,
+    auth: Option<AuthSession>,
+    max_in_flight: Option<usize>,
+    guest_tokens: Option<usize>,
+    retry: RetryPolicy,
+    endpoints: EndpointConfig,
+    discover_endpoints: bool,
+    transports: Vec<(String, Arc<dyn Transport>)>,
+    record_to: Option<PathBuf>,
+    replay_from: Option<PathBuf>,
+    session: Option<SessionState>,
+    session_file: Option<PathBuf>,
+    website_resolution: WebsiteResolution,
 }
 impl ScraperBuilder {
     pub fn new() -> Self {
@@ -143,84 +651,335 @@ impl ScraperBuilder {
     }
 
     pub fn with_proxy(mut self, addr: String) -> Self {
-        self.proxy = Some(addr);
+        self.proxies.push(addr);
         self
     }
 
+    // http(s):// or socks5:// proxies, requests and guest tokens are spread across all of them
+    pub fn with_proxies(mut self, addrs: Vec<String>) -> Self {
+        self.proxies.extend(addrs);
+        self
+    }
+
+    // used for every proxy, per proxy credentials can go in the url instead
     pub fn with_proxy_authentication(mut self, user: String, password: String) -> Self {
         self.proxy_auth = Some((user, password));
         self
     }
 
+    // takes a proxy out of rotation for `duration` after `failures` timeouts or 429s in a row
+    pub fn with_proxy_quarantine(mut self, failures: u32, duration: Duration) -> Self {
+        self.quarantine = Some((failures, duration));
+        self
+    }
+
     pub fn with_cookies(mut self, cookies: HashMap<String, String>) -> Self {
-        let mut jar = Jar::default();
+        let jar = Jar::default();
         for (cookiek, cookieb) in &cookies {
-            jar.add_cookie_str(
-                &format!("{cookiek}={cookieb}"),
-                "https://twitter.com".parse().unwrap(),
-            )
+            add_twitter_cookie(&jar, cookiek, cookieb);
         }
         self.cookie = Some(Arc::new(jar));
         self
     }
 
+    // logs in with an existing account session instead of activating guest tokens
+    pub fn with_auth_session(mut self, auth_token: String, csrf_token: String) -> Self {
+        self.auth = Some(AuthSession {
+            auth_token,
+            csrf_token,
+        });
+        self
+    }
+
+    // caps how many requests all clones of the scraper may have running at once
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = Some(max);
+        self
+    }
+
+    // activates this many guest tokens and rotates requests across them
+    pub fn with_guest_tokens(mut self, count: usize) -> Self {
+        self.guest_tokens = Some(count);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // when profile websites are followed through t.co, see `WebsiteResolution`
+    pub fn with_website_resolution(mut self, resolution: WebsiteResolution) -> Self {
+        self.website_resolution = resolution;
+        self
+    }
+
+    // overrides query ids, variables or features of the built in graphql endpoints
+    pub fn with_endpoints(mut self, config: EndpointConfig) -> Self {
+        self.endpoints.extend(config);
+        self
+    }
+
+    // refreshes the endpoints from the web client when finishing, `with_endpoints` still wins
+    pub fn with_endpoint_discovery(mut self) -> Self {
+        self.discover_endpoints = true;
+        self
+    }
+
     pub fn with_ua(mut self, ua: String) -> Self {
         self.user_agent = Some(ua);
         self
     }
 
+    // replaces the reqwest client entirely, the proxy, user agent and cookie options are then ignored
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transports = vec![("direct".to_string(), transport)];
+        self
+    }
+
+    // like `with_transport`, but one named transport per proxy
+    pub fn with_proxy_transports(mut self, transports: Vec<(String, Arc<dyn Transport>)>) -> Self {
+        self.transports.extend(transports);
+        self
+    }
+
+    // writes every request and response to cassettes in `dir`
+    pub fn with_recording(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.record_to = Some(dir.into());
+        self
+    }
+
+    // serves responses from cassettes in `dir` and never touches the network
+    pub fn with_replay(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.replay_from = Some(dir.into());
+        self
+    }
+
+    // picks up the guest tokens, cookies and rate limits of a saved session
+    // its bearer token and login replace the ones set on the builder
+    pub fn with_session(mut self, session: SessionState) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    // like `with_session`, loaded when finishing; a missing file starts a fresh session
+    pub fn with_session_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.session_file = Some(path.into());
+        self
+    }
+
     #[tracing::instrument]
     pub async fn finish(self) -> Result<Scraper, TwtScrapeError> {
         let ScraperBuilder {
             bearer_token,
             delay,
             variation,
-            proxy,
+            proxies,
             proxy_auth,
+            quarantine,
             user_agent,
             cookie,
+            auth,
+            max_in_flight,
+            guest_tokens,
+            retry,
+            endpoints,
+            discover_endpoints,
+            transports,
+            record_to,
+            replay_from,
+            session,
+            session_file,
+            website_resolution,
         } = self;
 
+        let session = match (session, session_file) {
+            (Some(session), _) => Some(session),
+            (None, Some(path)) if path.exists() => Some(SessionState::load(path).await?),
+            (None, _) => None,
+        };
+        let (bearer_token, auth) = match &session {
+            Some(session) => (session.bearer_token.clone(), session.auth.clone()),
+            None => (bearer_token, auth),
+        };
+
         let delayer = Delayer::new(
             delay.map(Duration::from_millis),
             variation.map(Duration::from_millis),
         );
 
         let jar = cookie.unwrap_or(Arc::new(Jar::default()));
+        if let Some(session) = &session {
+            for (name, value) in &session.cookies {
+                add_twitter_cookie(&jar, name, value);
+            }
+        }
+        if let Some(auth) = &auth {
+            add_twitter_cookie(&jar, "auth_token", &auth.auth_token);
+            add_twitter_cookie(&jar, "ct0", &auth.csrf_token);
+        }
 
-        let scpr = Scraper {
-            bearer_token,
-            client: {
-                let mut builder = Client::builder();
-                if let Some(ua) = user_agent {
-                    builder.user_agent(ua)
-                }
-                match proxy {
-                    Some(proxy) => {
-                        let mut proxybld = Proxy::https(proxy).map_err(InvalidProxy)?;
-                        if let Some((user, password)) = proxy_auth {
-                            proxybld = proxybld.basic_auth(&user, &password);
-                        }
-                        builder.proxy(proxybld)
+        let mut routes: Vec<(String, Arc<dyn Transport>)> = match replay_from {
+            Some(dir) => vec![("direct".to_string(), Arc::new(ReplayTransport::new(dir)))],
+            None if !transports.is_empty() => transports,
+            None if proxies.is_empty() => {
+                let client = build_client(user_agent.as_deref(), None, jar.clone())?;
+                vec![(
+                    "direct".to_string(),
+                    Arc::new(ReqwestTransport::new(client)),
+                )]
+            }
+            None => {
+                let mut routes: Vec<(String, Arc<dyn Transport>)> = vec![];
+                for addr in proxies {
+                    let mut proxy = Proxy::all(&addr).map_err(InvalidProxy)?;
+                    if let Some((user, password)) = &proxy_auth {
+                        proxy = proxy.basic_auth(user, password);
                     }
-                    None => builder,
+                    let client = build_client(user_agent.as_deref(), Some(proxy), jar.clone())?;
+                    routes.push((addr, Arc::new(ReqwestTransport::new(client))));
                 }
-                .timeout(Duration::from_secs(10))
-                .cookie_store(true)
-                .cookie_provider(jar.clone())
-                .build()
-                .map_err(TwtScrapeError::ClientBuildError)?
-            },
-            delayer,
-            guest_token: TimedToken::new(),
+                routes
+            }
+        };
+
+        if let Some(dir) = record_to {
+            let recorder = RecordingTransport::new(routes[0].1.clone(), dir);
+            routes = routes
+                .into_iter()
+                .map(|(name, transport)| {
+                    let recording: Arc<dyn Transport> = Arc::new(recorder.sharing(transport));
+                    (name, recording)
+                })
+                .collect();
+        }
+
+        let mut pool = match quarantine {
+            Some((failures, duration)) => ProxyPool::new(failures, duration),
+            None => ProxyPool::default(),
+        };
+        for (name, transport) in routes {
+            pool.push(name, transport);
+        }
+        let pool_len = pool.len();
+
+        let scpr = Scraper {
+            bearer_token: bearer_token.into(),
+            proxies: Arc::new(pool),
+            endpoints: Arc::new(RwLock::new({
+                let mut defaults = Endpoints::default();
+                defaults.apply(&endpoints);
+                defaults
+            })),
+            delayer: Arc::new(delayer),
+            auth: auth.map(Arc::new),
+            // at least one token per proxy, or some would never be used
+            guest_token: Arc::new(TokenPool::new(
+                guest_tokens
+                    .unwrap_or_else(|| {
+                        session
+                            .as_ref()
+                            .map_or(1, |session| session.guest_tokens.len())
+                    })
+                    .max(pool_len),
+                Duration::from_secs(60 * 60 * 3),
+            )),
+            rate_limits: Arc::new(RateLimits::default()),
+            retry: Arc::new(retry),
             cookie: jar,
+            in_flight: max_in_flight.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            website_resolution,
         };
-        let token = scpr.refresh_token().await?;
-        scpr.guest_token
-            .init(Duration::from_secs(60 * 60 * 3), token)
-            .await;
-        Ok(scpr)
+        let mut saved_tokens = vec![];
+        if let Some(session) = session {
+            for (endpoint, limit) in session.rate_limits {
+                scpr.rate_limits.update(endpoint, limit);
+            }
+            saved_tokens = session.guest_tokens;
+        }
+
+        // stale defaults still mostly work, so a failed discovery isn't fatal
+        if discover_endpoints {
+            match scpr.refresh_endpoints().await {
+                Ok(_) => scpr.update_endpoints(&endpoints),
+                Err(why) => {
+                    warn!(error = %why, "Could not discover endpoints, using the defaults.")
+                }
+            }
+        }
+
+        if scpr.session_mode() == SessionMode::Authenticated {
+            return Ok(scpr);
+        }
+
+        // A saved token goes back on the proxy that activated it. A proxy that can't activate one
+        // leaves its slot empty to be retried on use, unless none of them can.
+        let now = SystemTime::now();
+        let mut ready = 0;
+        let mut last_error = None;
+        for slot in 0..scpr.guest_token.len() {
+            let route = scpr.route_of(slot);
+            let saved = saved_tokens
+                .iter()
+                .position(|saved| {
+                    saved
+                        .proxy
+                        .iter()
+                        .all(|proxy| proxy == scpr.proxies.name(route))
+                })
+                .map(|at| saved_tokens.remove(at));
+            let restored = match saved {
+                Some(saved) => {
+                    let activated = UNIX_EPOCH + Duration::from_secs(saved.activated);
+                    let age = now.duration_since(activated).unwrap_or_default();
+                    scpr.guest_token.restore(slot, saved.token, age).await
+                }
+                None => false,
+            };
+            if restored {
+                ready += 1;
+                continue;
+            }
+
+            match scpr.refresh_token(route).await {
+                Ok(token) => {
+                    scpr.guest_token.init(slot, token).await;
+                    ready += 1;
+                }
+                Err(why) => {
+                    warn!(proxy = scpr.proxies.name(route), error = %why, "Could not activate a guest token.");
+                    last_error = Some(why);
+                }
+            }
+        }
+
+        match last_error {
+            Some(why) if ready == 0 => Err(why),
+            _ => Ok(scpr),
+        }
+    }
+}
+
+fn build_client(user_agent: Option<&str>, proxy: Option<Proxy>, jar: Arc<Jar>) -> SResult<Client> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .cookie_store(true)
+        .cookie_provider(jar);
+    if let Some(ua) = user_agent {
+        builder = builder.user_agent(ua);
     }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(TwtScrapeError::ClientBuildError)
+}
+
+// scoped to twitter.com and every subdomain, api.twitter.com needs them too
+fn add_twitter_cookie(jar: &Jar, name: &str, value: &str) {
+    jar.add_cookie_str(
+        &format!("{name}={value}; Domain=twitter.com; Path=/"),
+        &TWITTER_URL.parse::<Url>().unwrap(),
+    )
 }
 
 impl Default for ScraperBuilder {
@@ -229,10 +988,23 @@ impl Default for ScraperBuilder {
             bearer_token: "AAAAAAAAAAAAAAAAAAAAAPYXBAAAAAAACLXUNDekMxqa8h%2F40K4moUkGsoc%3DTYfbDKbT3jJPCEVnMYqilB28NHfOPqkca3qaAxGfsyKCs0wRbw".into(),
             delay: None,
             cookie: None,
-            proxy: None,
+            auth: None,
+            max_in_flight: None,
+            guest_tokens: None,
+            retry: RetryPolicy::default(),
+            endpoints: EndpointConfig::default(),
+            discover_endpoints: false,
+            proxies: vec![],
             proxy_auth: None,
+            quarantine: None,
             variation: None,
-            user_agent: None
+            user_agent: None,
+            transports: vec![],
+            record_to: None,
+            replay_from: None,
+            session: None,
+            session_file: None,
+            website_resolution: WebsiteResolution::default(),
         }
     }
 }
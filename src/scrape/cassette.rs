@@ -0,0 +1,203 @@
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::SResult;
+use crate::error::TwtScrapeError::CassetteError;
+use crate::scrape::transport::{ApiRequest, ApiResponse, Transport, TransportFuture};
+
+// A cassette is one recorded request/response pair, stored as `<operation>-<hash>-<n>.json`.
+// `n` counts how many times the same request has been made, so paginating over the same url
+// replays the pages in the order they were recorded.
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub final_url: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Cassette {
+    fn from_exchange(request: &ApiRequest, response: &ApiResponse) -> Self {
+        Cassette {
+            method: request.method.to_string(),
+            url: request.url.clone(),
+            status: response.status,
+            final_url: response.url.clone(),
+            headers: response.headers.clone(),
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+        }
+    }
+
+    fn into_response(self) -> ApiResponse {
+        ApiResponse {
+            status: self.status,
+            url: self.final_url,
+            headers: self.headers,
+            body: self.body.into_bytes(),
+        }
+    }
+}
+
+// FNV-1a, ahash is seeded per process so it can't name files
+fn stable_hash(data: &str) -> u64 {
+    data.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn cassette_key(request: &ApiRequest) -> String {
+    let path = request.url.split('?').next().unwrap_or_default();
+    let operation = path
+        .rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("request")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let hash = stable_hash(&format!("{} {}", request.method, request.url));
+    format!("{operation}-{hash:016x}")
+}
+
+fn cassette_path(dir: &Path, key: &str, index: usize) -> PathBuf {
+    dir.join(format!("{key}-{index}.json"))
+}
+
+fn next_index(counters: &Mutex<HashMap<String, usize>>, key: &str) -> usize {
+    let mut counters = counters.lock().unwrap();
+    let counter = counters.entry(key.to_string()).or_default();
+    let index = *counter;
+    *counter += 1;
+    index
+}
+
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    dir: PathBuf,
+    counters: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn Transport>, dir: impl Into<PathBuf>) -> Self {
+        RecordingTransport {
+            inner,
+            dir: dir.into(),
+            counters: Arc::default(),
+        }
+    }
+
+    // records another transport into the same cassettes, e.g. one per proxy
+    pub fn sharing(&self, inner: Arc<dyn Transport>) -> Self {
+        RecordingTransport {
+            inner,
+            dir: self.dir.clone(),
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn execute(&self, request: ApiRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let key = cassette_key(&request);
+            let response = self.inner.execute(request.clone()).await?;
+
+            let cassette = Cassette::from_exchange(&request, &response);
+            let serialized = serde_json::to_vec_pretty(&cassette)
+                .map_err(|why| CassetteError(why.to_string()))?;
+
+            tokio::fs::create_dir_all(&self.dir)
+                .await
+                .map_err(|why| CassetteError(why.to_string()))?;
+            let path = cassette_path(&self.dir, &key, next_index(&self.counters, &key));
+            tokio::fs::write(&path, serialized)
+                .await
+                .map_err(|why| CassetteError(format!("{}: {why}", path.display())))?;
+
+            Ok(response)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+    counters: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ReplayTransport {
+            dir: dir.into(),
+            counters: Mutex::default(),
+        }
+    }
+
+    async fn load(&self, request: &ApiRequest) -> SResult<ApiResponse> {
+        let key = cassette_key(request);
+        let index = next_index(&self.counters, &key);
+
+        // past the end of the recording, keep serving the last recorded response
+        for index in (0..=index).rev() {
+            let path = cassette_path(&self.dir, &key, index);
+            match tokio::fs::read(&path).await {
+                Ok(data) => {
+                    let cassette: Cassette = serde_json::from_slice(&data)
+                        .map_err(|why| CassetteError(format!("{}: {why}", path.display())))?;
+                    return Ok(cassette.into_response());
+                }
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(why) => return Err(CassetteError(format!("{}: {why}", path.display()))),
+            }
+        }
+
+        Err(CassetteError(format!(
+            "No cassette recorded for {} {}",
+            request.method, request.url
+        )))
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn execute(&self, request: ApiRequest) -> TransportFuture<'_> {
+        Box::pin(async move { self.load(&request).await })
+    }
+}
+
+#[test]
+fn record_then_replay() {
+    use crate::scrape::transport::{block_on, FixtureTransport};
+
+    block_on(async {
+        let dir = std::env::temp_dir().join(format!("twtscrape-cassette-{}", std::process::id()));
+        let url = "https://twitter.com/i/api/graphql/abc/TweetDetail?variables=1";
+
+        let fixture = FixtureTransport::new()
+            .with_json(url, "{\"page\":1}")
+            .with_json(url, "{\"page\":2}");
+        let recorder = RecordingTransport::new(Arc::new(fixture), &dir);
+        for _ in 0..2 {
+            recorder.execute(ApiRequest::get(url)).await.unwrap();
+        }
+
+        let replay = ReplayTransport::new(&dir);
+        let mut pages = vec![];
+        for _ in 0..3 {
+            let response = replay.execute(ApiRequest::get(url)).await.unwrap();
+            pages.push(String::from_utf8(response.body).unwrap());
+        }
+        assert_eq!(pages, vec!["{\"page\":1}", "{\"page\":2}", "{\"page\":2}"]);
+
+        assert!(replay
+            .execute(ApiRequest::get("https://twitter.com/other"))
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    });
+}
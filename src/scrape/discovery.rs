@@ -0,0 +1,183 @@
+use ahash::HashMap;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use url::Url;
+
+use crate::error::SResult;
+use crate::error::TwtScrapeError::DiscoveryError;
+use crate::scrape::endpoints::{EndpointConfig, EndpointOverride, Endpoints, Operation};
+use crate::scrape::{Scraper, TWITTER_URL};
+
+// The web client's `main.<hash>.js` bundle lists every graphql operation as
+// `{queryId:"..",operationName:"..",operationType:"..",metadata:{featureSwitches:[..],..}}`,
+// and the twitter.com page itself carries the current value of each feature switch.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredOperation {
+    pub query_id: String,
+    pub operation_name: String,
+    pub feature_switches: Vec<String>,
+}
+
+// url of the main bundle in the twitter.com page
+pub fn find_main_bundle(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("script[src]").ok()?;
+    let src = document
+        .select(&selector)
+        .filter_map(|script| script.value().attr("src"))
+        .find(|src| {
+            let file = src.rsplit('/').next().unwrap_or_default();
+            file.starts_with("main.") && file.ends_with(".js")
+        })?;
+    Url::parse(TWITTER_URL)
+        .ok()?
+        .join(src)
+        .ok()
+        .map(|url| url.to_string())
+}
+
+fn quoted_after<'a>(haystack: &'a str, key: &str) -> Option<&'a str> {
+    let start = haystack.find(key)? + key.len();
+    let rest = &haystack[start..];
+    rest.find('"').map(|end| &rest[..end])
+}
+
+pub fn parse_bundle(js: &str) -> Vec<DiscoveredOperation> {
+    js.split("queryId:\"")
+        .skip(1)
+        .filter_map(|chunk| {
+            let query_id = &chunk[..chunk.find('"')?];
+            let operation_name = quoted_after(chunk, "operationName:\"")?;
+            let feature_switches = chunk
+                .find("featureSwitches:[")
+                .and_then(|start| {
+                    let list = &chunk[start + "featureSwitches:[".len()..];
+                    list.find(']').map(|end| &list[..end])
+                })
+                .map(|list| {
+                    list.split(',')
+                        .map(|name| name.trim().trim_matches('"'))
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Some(DiscoveredOperation {
+                query_id: query_id.to_string(),
+                operation_name: operation_name.to_string(),
+                feature_switches,
+            })
+        })
+        .collect()
+}
+
+// `"name":{"value":true}` entries of the page's feature switch config, other value types are skipped
+pub fn parse_feature_values(html: &str) -> HashMap<String, bool> {
+    const MARKER: &str = "\":{\"value\":";
+
+    let mut values = HashMap::default();
+    let mut rest = html;
+    while let Some(at) = rest.find(MARKER) {
+        let name = rest[..at].rsplit('"').next().unwrap_or_default();
+        let after = &rest[at + MARKER.len()..];
+        if after.starts_with("true") {
+            values.insert(name.to_string(), true);
+        } else if after.starts_with("false") {
+            values.insert(name.to_string(), false);
+        }
+        rest = after;
+    }
+    values
+}
+
+// Only operations the scraper knows about are kept. A switch missing from the page keeps
+// the value the endpoint already had, or false.
+pub fn to_config(
+    operations: &[DiscoveredOperation],
+    feature_values: &HashMap<String, bool>,
+    current: &Endpoints,
+) -> EndpointConfig {
+    operations
+        .iter()
+        .filter_map(|discovered| {
+            let operation = Operation::from_name(&discovered.operation_name)?;
+            let endpoint = current.get(operation);
+            let mut update = EndpointOverride::query_id(&discovered.query_id);
+            for name in &discovered.feature_switches {
+                let enabled = feature_values
+                    .get(name)
+                    .copied()
+                    .or_else(|| endpoint.features.get(name).and_then(Value::as_bool))
+                    .unwrap_or(false);
+                update = update.with_feature(name, enabled);
+            }
+            Some((operation, update))
+        })
+        .collect()
+}
+
+#[tracing::instrument]
+pub async fn discover_endpoints(scraper: &Scraper) -> SResult<EndpointConfig> {
+    let page = scraper.fetch_page(TWITTER_URL).await?;
+    let html = String::from_utf8_lossy(&page.body);
+    let bundle_url = find_main_bundle(&html)
+        .ok_or_else(|| DiscoveryError("No main.js bundle on twitter.com".to_string()))?;
+
+    let bundle = scraper.fetch_page(&bundle_url).await?;
+    let operations = parse_bundle(&String::from_utf8_lossy(&bundle.body));
+    if operations.is_empty() {
+        return Err(DiscoveryError(format!("No operations in {bundle_url}")));
+    }
+
+    Ok(to_config(
+        &operations,
+        &parse_feature_values(&html),
+        &scraper.endpoints(),
+    ))
+}
+
+#[test]
+fn discover_from_fixture() {
+    use crate::scrape::transport::{block_on, FixtureTransport};
+    use crate::scrape::ScraperBuilder;
+    use std::sync::Arc;
+
+    block_on(async {
+        let html = include_str!("../../fixtures/discovery/twitter.html");
+        let bundle_url = "https://abs.twimg.com/responsive-web/client-web/main.4c2fa8d1.js";
+        assert_eq!(find_main_bundle(html).as_deref(), Some(bundle_url));
+
+        let transport = FixtureTransport::new()
+            .with_guest_token("1234")
+            .with_json(bundle_url, include_str!("../../fixtures/discovery/main.js"))
+            .with_json(TWITTER_URL, html);
+        let scraper = ScraperBuilder::new()
+            .with_transport(Arc::new(transport))
+            .with_endpoint_discovery()
+            .finish()
+            .await
+            .unwrap();
+
+        let endpoints = scraper.endpoints();
+        let user = endpoints.get(Operation::UserByScreenName);
+        assert_eq!(user.query_id, "xc8f1g7BYqr6VTzTbvNlGw");
+        assert_eq!(user.features["hidden_profile_likes_enabled"], true);
+        assert_eq!(user.features["verified_phone_label_enabled"], false);
+
+        let detail = endpoints.get(Operation::TweetDetail);
+        assert_eq!(detail.query_id, "3yX7xr2hKjcZYnXt6cU6lQ");
+        // not in the page's config, kept from the defaults
+        assert_eq!(
+            detail.features["responsive_web_enhance_cards_enabled"],
+            true
+        );
+
+        // not in the bundle
+        assert_eq!(
+            endpoints.get(Operation::Followers).query_id,
+            "_gXC5CopoM8fIgawvyGpIg"
+        );
+    });
+}
@@ -0,0 +1,307 @@
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+// Twitter's web client addresses graphql operations as `<query id>/<operation name>`, with the
+// variables and feature switches as url encoded json. Query ids and features rotate whenever
+// the web client ships, so every part of an endpoint can be replaced at runtime.
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    UserByScreenName,
+    UserByRestId,
+    UsersByRestIds,
+    Following,
+    Followers,
+    TweetDetail,
+    UserTweetsAndReplies,
+    ModeratedTimeline,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 8] = [
+        Operation::UserByScreenName,
+        Operation::UserByRestId,
+        Operation::UsersByRestIds,
+        Operation::Following,
+        Operation::Followers,
+        Operation::TweetDetail,
+        Operation::UserTweetsAndReplies,
+        Operation::ModeratedTimeline,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::UserByScreenName => "UserByScreenName",
+            Operation::UserByRestId => "UserByRestId",
+            Operation::UsersByRestIds => "UsersByRestIds",
+            Operation::Following => "Following",
+            Operation::Followers => "Followers",
+            Operation::TweetDetail => "TweetDetail",
+            Operation::UserTweetsAndReplies => "UserTweetsAndReplies",
+            Operation::ModeratedTimeline => "ModeratedTimeline",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub query_id: String,
+    // sent with every request, each call adds its ids and cursor on top
+    pub variables: Map<String, Value>,
+    pub features: Map<String, Value>,
+}
+
+impl Endpoint {
+    pub fn url(&self, operation: Operation, variables: Value) -> String {
+        let mut all = self.variables.clone();
+        if let Value::Object(variables) = variables {
+            all.extend(variables);
+        }
+        format!(
+            "https://twitter.com/i/api/graphql/{}/{}?variables={}&features={}",
+            self.query_id,
+            operation.name(),
+            urlencoding::encode(&Value::Object(all).to_string()),
+            urlencoding::encode(&Value::Object(self.features.clone()).to_string()),
+        )
+    }
+
+    pub fn apply(&mut self, update: &EndpointOverride) {
+        if let Some(query_id) = &update.query_id {
+            self.query_id = query_id.clone();
+        }
+        self.variables.extend(update.variables.clone());
+        self.features.extend(update.features.clone());
+    }
+}
+
+// A partial endpoint, from config or discovery. Unset parts keep their current value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointOverride {
+    #[serde(default)]
+    pub query_id: Option<String>,
+    #[serde(default)]
+    pub variables: Map<String, Value>,
+    #[serde(default)]
+    pub features: Map<String, Value>,
+}
+
+impl EndpointOverride {
+    pub fn query_id(query_id: impl Into<String>) -> Self {
+        EndpointOverride {
+            query_id: Some(query_id.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_variable(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    pub fn with_feature(mut self, name: impl Into<String>, enabled: bool) -> Self {
+        self.features.insert(name.into(), Value::Bool(enabled));
+        self
+    }
+}
+
+// e.g. `{"TweetDetail": {"query_id": "...", "features": {"vibe_api_enabled": false}}}`
+pub type EndpointConfig = HashMap<Operation, EndpointOverride>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Endpoints {
+    endpoints: HashMap<Operation, Endpoint>,
+}
+
+impl Endpoints {
+    pub fn get(&self, operation: Operation) -> &Endpoint {
+        // every operation is filled in by default
+        &self.endpoints[&operation]
+    }
+
+    pub fn url(&self, operation: Operation, variables: Value) -> String {
+        self.get(operation).url(operation, variables)
+    }
+
+    pub fn apply(&mut self, config: &EndpointConfig) {
+        for (operation, update) in config {
+            if let Some(endpoint) = self.endpoints.get_mut(operation) {
+                endpoint.apply(update);
+            }
+        }
+    }
+}
+
+fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+fn timeline_features() -> Map<String, Value> {
+    object(json!({
+        "responsive_web_twitter_blue_verified_badge_is_enabled": true,
+        "verified_phone_label_enabled": false,
+        "responsive_web_graphql_timeline_navigation_enabled": true,
+        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": true,
+        "tweetypie_unmention_optimization_enabled": true,
+        "responsive_web_uc_gql_enabled": true,
+        "vibe_api_enabled": true,
+        "responsive_web_edit_tweet_api_enabled": true,
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
+        "standardized_nudges_misinfo": true,
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": false,
+        "interactive_text_enabled": true,
+        "responsive_web_text_conversations_enabled": false,
+        "responsive_web_enhance_cards_enabled": true,
+    }))
+}
+
+fn user_variables() -> Map<String, Value> {
+    object(json!({
+        "withSafetyModeUserFields": true,
+        "withSuperFollowsUserFields": true,
+    }))
+}
+
+fn user_features() -> Map<String, Value> {
+    object(json!({
+        "responsive_web_twitter_blue_verified_badge_is_enabled": true,
+        "verified_phone_label_enabled": false,
+        "responsive_web_graphql_timeline_navigation_enabled": true,
+    }))
+}
+
+fn follow_variables() -> Map<String, Value> {
+    object(json!({
+        "count": 20,
+        "includePromotedContent": false,
+        "withSuperFollowsUserFields": true,
+        "withDownvotePerspective": false,
+        "withReactionsMetadata": false,
+        "withReactionsPerspective": false,
+        "withSuperFollowsTweetFields": true,
+    }))
+}
+
+fn default_endpoint(operation: Operation) -> Endpoint {
+    let (query_id, variables, features) = match operation {
+        Operation::UserByScreenName => {
+            ("ptQPCD7NrFS_TW71Lq07nw", user_variables(), user_features())
+        }
+        Operation::UserByRestId => ("GazOglcBvgLigl3ywt6b3Q", user_variables(), user_features()),
+        Operation::UsersByRestIds => ("OJBgJQIrij6e3cjqQ3Zu1Q", user_variables(), user_features()),
+        Operation::Following => (
+            "9rGM7YNDYuiqd0Cb0ZwLJw",
+            follow_variables(),
+            timeline_features(),
+        ),
+        Operation::Followers => (
+            "_gXC5CopoM8fIgawvyGpIg",
+            follow_variables(),
+            timeline_features(),
+        ),
+        Operation::TweetDetail => (
+            "BoHLKeBvibdYDiJON1oqTg",
+            object(json!({
+                "with_rux_injections": false,
+                "includePromotedContent": false,
+                "withCommunity": true,
+                "withQuickPromoteEligibilityTweetFields": true,
+                "withBirdwatchNotes": false,
+                "withSuperFollowsUserFields": true,
+                "withDownvotePerspective": false,
+                "withReactionsMetadata": false,
+                "withReactionsPerspective": false,
+                "withSuperFollowsTweetFields": true,
+                "withVoice": true,
+                "withV2Timeline": true,
+            })),
+            timeline_features(),
+        ),
+        Operation::UserTweetsAndReplies => (
+            "s0hG9oAmWEYVBqOLJP-TBQ",
+            object(json!({
+                "count": 40,
+                "includePromotedContent": false,
+                "withCommunity": true,
+                "withSuperFollowsUserFields": true,
+                "withDownvotePerspective": false,
+                "withReactionsMetadata": false,
+                "withReactionsPerspective": false,
+                "withSuperFollowsTweetFields": true,
+                "withVoice": true,
+                "withV2Timeline": true,
+            })),
+            timeline_features(),
+        ),
+        Operation::ModeratedTimeline => (
+            "c9IdrvgCZw7oxPZFPBpyrg",
+            follow_variables(),
+            timeline_features(),
+        ),
+    };
+
+    Endpoint {
+        query_id: query_id.to_string(),
+        variables,
+        features,
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            endpoints: Operation::ALL
+                .into_iter()
+                .map(|operation| (operation, default_endpoint(operation)))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn build_and_override_endpoint_urls() {
+    let mut endpoints = Endpoints::default();
+    let url = endpoints.url(
+        Operation::Following,
+        json!({"userId": "12", "cursor": "a b"}),
+    );
+    assert!(url.starts_with("https://twitter.com/i/api/graphql/9rGM7YNDYuiqd0Cb0ZwLJw/Following?"));
+
+    let query = url::Url::parse(&url).unwrap();
+    let params = query.query_pairs().collect::<HashMap<_, _>>();
+    let variables: Value = serde_json::from_str(&params["variables"]).unwrap();
+    assert_eq!(variables["userId"], "12");
+    assert_eq!(variables["cursor"], "a b");
+    assert_eq!(variables["count"], 20);
+
+    let config: EndpointConfig = serde_json::from_str(
+        r#"{"Following": {"query_id": "newid", "features": {"vibe_api_enabled": false}}}"#,
+    )
+    .unwrap();
+    endpoints.apply(&config);
+    let url = endpoints.url(Operation::Following, json!({"userId": "12"}));
+    assert!(url.contains("/newid/Following?"));
+    assert!(url.contains(&*urlencoding::encode("\"vibe_api_enabled\":false")));
+    assert_eq!(
+        endpoints.get(Operation::Followers).query_id,
+        "_gXC5CopoM8fIgawvyGpIg"
+    );
+
+    assert_eq!(
+        Operation::from_name("UsersByRestIds"),
+        Some(Operation::UsersByRestIds)
+    );
+    let url = endpoints.url(Operation::UsersByRestIds, json!({"userIds": ["1", "2"]}));
+    assert!(url.contains("/OJBgJQIrij6e3cjqQ3Zu1Q/UsersByRestIds?"));
+    assert!(url.contains(&*urlencoding::encode("\"userIds\":[\"1\",\"2\"]")));
+}
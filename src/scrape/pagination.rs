@@ -0,0 +1,531 @@
+use ahash::{HashSet, HashSetExt};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::future::Future;
+
+use crate::error::{SResult, TwtScrapeError};
+
+// Every timeline is a chain of pages linked by a bottom cursor. Streams yield each page
+// as soon as it's parsed, so nothing has to wait for the end of the timeline.
+
+// Where a crawl got to. Save the checkpoint of the last page you handled and pass it back
+// to a `*_from` stream to carry on from the next page after a crash, restart or ban.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    // cursor of the next page, None to start from the top
+    pub cursor: Option<String>,
+    pub pages: u64,
+    pub finished: bool,
+    // end of the date window a sliced search was in
+    #[serde(default)]
+    pub window: Option<DateTime<Utc>>,
+}
+
+impl Checkpoint {
+    pub fn at(cursor: impl Into<String>) -> Self {
+        Checkpoint {
+            cursor: Some(cursor.into()),
+            ..Self::default()
+        }
+    }
+}
+
+// an entry that was skipped, the rest of its page still goes through
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemError {
+    // id of the tweet or user, or the timeline entry id
+    pub item: String,
+    pub error: String,
+}
+
+impl ItemError {
+    pub fn new(item: impl Display, error: &TwtScrapeError) -> Self {
+        ItemError {
+            item: item.to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // where the next page starts, None on the last page
+    pub next_cursor: Option<String>,
+    // filled in by `paginate`, resumes right after this page
+    #[serde(default)]
+    pub checkpoint: Checkpoint,
+    #[serde(default)]
+    pub errors: Vec<ItemError>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Self {
+        Page {
+            items,
+            next_cursor,
+            checkpoint: Checkpoint::default(),
+            errors: vec![],
+        }
+    }
+
+    pub fn with_errors(mut self, errors: Vec<ItemError>) -> Self {
+        self.errors = errors;
+        self
+    }
+}
+
+// Whatever a crawl got before it stopped. `checkpoint` is the last good page, so passing it
+// to the matching `*_from`/`*_partial` call picks the crawl back up.
+#[derive(Debug)]
+pub struct PartialResult<T> {
+    pub data: T,
+    pub checkpoint: Checkpoint,
+    pub errors: Vec<ItemError>,
+    // the request that ended the crawl early
+    pub failure: Option<TwtScrapeError>,
+}
+
+impl<T> PartialResult<T> {
+    pub fn new(data: T, checkpoint: Checkpoint) -> Self {
+        PartialResult {
+            data,
+            checkpoint,
+            errors: vec![],
+            failure: None,
+        }
+    }
+
+    pub fn failed(data: T, checkpoint: Checkpoint, failure: TwtScrapeError) -> Self {
+        PartialResult {
+            failure: Some(failure),
+            ..Self::new(data, checkpoint)
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failure.is_none() && self.errors.is_empty()
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> PartialResult<U> {
+        PartialResult {
+            data: f(self.data),
+            checkpoint: self.checkpoint,
+            errors: self.errors,
+            failure: self.failure,
+        }
+    }
+
+    // all or nothing, skipped items don't count as a failure
+    pub fn into_result(self) -> SResult<T> {
+        match self.failure {
+            Some(why) => Err(why),
+            None => Ok(self.data),
+        }
+    }
+}
+
+// Runs a page stream to the end or its first error, keeping every page that made it.
+pub async fn collect_partial<T>(
+    pages: impl Stream<Item = SResult<Page<T>>>,
+    start: Checkpoint,
+) -> PartialResult<Vec<T>> {
+    let mut result = PartialResult::new(vec![], start);
+    let mut pages = Box::pin(pages);
+    while let Some(page) = pages.next().await {
+        match page {
+            Ok(page) => {
+                result.data.extend(page.items);
+                result.errors.extend(page.errors);
+                result.checkpoint = page.checkpoint;
+            }
+            Err(why) => {
+                result.failure = Some(why);
+                break;
+            }
+        }
+    }
+    result
+}
+
+// Fetches pages starting at `start` until twitter returns no cursor, a cursor it already
+// handed out, or an empty page (its timelines keep handing out cursors past the end).
+// A page whose entries all failed to parse isn't empty, the crawl goes on past it.
+// The stream ends after the first error, the last page's checkpoint is still good.
+pub fn paginate<'a, T, F, Fut>(
+    start: Checkpoint,
+    fetch: F,
+) -> impl Stream<Item = SResult<Page<T>>> + 'a
+where
+    T: 'a,
+    F: FnMut(Option<String>) -> Fut + 'a,
+    Fut: Future<Output = SResult<Page<T>>> + 'a,
+{
+    let mut seen = HashSet::new();
+    seen.extend(start.cursor.clone());
+    let state = (!start.finished).then_some((start, fetch, seen));
+    stream::unfold(state, |state| async move {
+        let (checkpoint, mut fetch, mut seen) = state?;
+        match fetch(checkpoint.cursor.clone()).await {
+            Ok(mut page) => {
+                let next = page.next_cursor.clone().filter(|next| {
+                    let empty = page.items.is_empty() && page.errors.is_empty();
+                    !empty && seen.insert(next.clone())
+                });
+                page.checkpoint = Checkpoint {
+                    finished: next.is_none(),
+                    cursor: next.or(checkpoint.cursor),
+                    pages: checkpoint.pages + 1,
+                    window: checkpoint.window,
+                };
+                let next =
+                    (!page.checkpoint.finished).then(|| (page.checkpoint.clone(), fetch, seen));
+                Some((Ok(page), next))
+            }
+            Err(why) => Some((Err(why), None)),
+        }
+    })
+}
+
+// Stops after `max_pages` pages or `max_items` items, cutting the last page short.
+// A cut page keeps the checkpoint of the page before it (`start` for the first page), so
+// resuming from it fetches the cut page again instead of skipping what was dropped.
+// The page that reaches a limit is the last one requested.
+pub fn limit<'a, T: 'a>(
+    pages: impl Stream<Item = SResult<Page<T>>> + 'a,
+    start: Checkpoint,
+    max_pages: Option<u64>,
+    max_items: Option<usize>,
+) -> impl Stream<Item = SResult<Page<T>>> + 'a {
+    let state = (Box::pin(pages), start, 0u64, 0usize);
+    stream::unfold(
+        state,
+        move |(mut pages, last, pages_seen, items_seen)| async move {
+            let done = matches!(max_pages, Some(max) if pages_seen >= max)
+                || matches!(max_items, Some(max) if items_seen >= max);
+            if done {
+                return None;
+            }
+
+            match pages.next().await? {
+                Ok(mut page) => {
+                    let left = max_items.map(|max| max - items_seen);
+                    if let Some(left) = left.filter(|left| page.items.len() > *left) {
+                        page.items.truncate(left);
+                        page.checkpoint = last.clone();
+                    }
+                    let items_seen = items_seen + page.items.len();
+                    let last = page.checkpoint.clone();
+                    Some((Ok(page), (pages, last, pages_seen + 1, items_seen)))
+                }
+                Err(why) => Some((Err(why), (pages, last, pages_seen, items_seen))),
+            }
+        },
+    )
+}
+
+// flattens a page stream into its items
+pub fn items<'a, T: 'a>(
+    pages: impl Stream<Item = SResult<Page<T>>> + 'a,
+) -> impl Stream<Item = SResult<T>> + 'a {
+    pages
+        .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+#[test]
+fn paginate_until_cursor_runs_out() {
+    crate::scrape::transport::block_on(async {
+        let fetch = |cursor: Option<String>| async move {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            Ok(Page::new(
+                vec![page * 10, page * 10 + 1],
+                (page < 2).then(|| (page + 1).to_string()),
+            ))
+        };
+        let all: Vec<u32> = items(paginate(Checkpoint::default(), fetch))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all, vec![0, 1, 10, 11, 20, 21]);
+
+        // a repeated cursor ends the stream instead of looping forever
+        let stuck = |_| async { Ok(Page::new(vec![1], Some("same".to_string()))) };
+        let pages = paginate(Checkpoint::at("same"), stuck)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(pages.len(), 1);
+
+        // so does a cursor from further back
+        let cycle = |cursor: Option<String>| async move {
+            let next = if cursor.as_deref() == Some("a") {
+                "b"
+            } else {
+                "a"
+            };
+            Ok(Page::new(vec![1], Some(next.to_string())))
+        };
+        let pages = paginate(Checkpoint::default(), cycle)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(pages.len(), 3);
+
+        let failing =
+            |_| async { Err::<Page<u32>, _>(crate::error::TwtScrapeError::SchemaAccessErr) };
+        let pages = paginate(Checkpoint::default(), failing)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(pages.as_slice(), [Err(_)]));
+    });
+}
+
+#[test]
+fn keep_pages_before_failure() {
+    crate::scrape::transport::block_on(async {
+        let fetch = |cursor: Option<String>| async move {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            if page == 2 {
+                return Err(TwtScrapeError::SchemaAccessErr);
+            }
+            let errors = vec![ItemError::new(page, &TwtScrapeError::UserResultError)];
+            Ok(Page::new(vec![page], Some((page + 1).to_string())).with_errors(errors))
+        };
+
+        let partial = collect_partial(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+        )
+        .await;
+        assert_eq!(partial.data, vec![0, 1]);
+        assert_eq!(partial.errors.len(), 2);
+        assert_eq!(partial.errors[1].item, "1");
+        assert_eq!(partial.checkpoint.cursor.as_deref(), Some("2"));
+        assert!(matches!(
+            partial.failure,
+            Some(TwtScrapeError::SchemaAccessErr)
+        ));
+        assert!(partial.into_result().is_err());
+    });
+}
+
+#[test]
+fn keep_going_past_pages_of_errors() {
+    crate::scrape::transport::block_on(async {
+        // page 1 only has entries that failed to parse, page 3 is twitter's empty tail
+        let fetch = |cursor: Option<String>| async move {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            let next = Some((page + 1).to_string());
+            Ok(match page {
+                1 => Page::new(vec![], next).with_errors(vec![ItemError::new(
+                    "tweet-1",
+                    &TwtScrapeError::SchemaAccessErr,
+                )]),
+                3 => Page::new(vec![], next),
+                _ => Page::new(vec![page], next),
+            })
+        };
+
+        let partial = collect_partial(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+        )
+        .await;
+        assert_eq!(partial.data, vec![0, 2]);
+        assert_eq!(partial.errors.len(), 1);
+        assert_eq!(partial.errors[0].item, "tweet-1");
+        assert_eq!(partial.checkpoint.pages, 4);
+        assert!(partial.checkpoint.finished);
+    });
+}
+
+#[test]
+fn resume_from_checkpoint() {
+    crate::scrape::transport::block_on(async {
+        // page 1 fails the first time around
+        let attempts = std::cell::Cell::new(0);
+        let fetch = |cursor: Option<String>| {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            attempts.set(attempts.get() + 1);
+            let fail = page == 1 && attempts.get() == 2;
+            async move {
+                if fail {
+                    return Err(crate::error::TwtScrapeError::SchemaAccessErr);
+                }
+                Ok(Page::new(
+                    vec![page],
+                    (page < 2).then(|| (page + 1).to_string()),
+                ))
+            }
+        };
+
+        let mut saved = Checkpoint::default();
+        let mut seen = vec![];
+        let mut pages = Box::pin(paginate(saved.clone(), &fetch));
+        while let Some(Ok(page)) = pages.next().await {
+            seen.extend(page.items);
+            saved = page.checkpoint;
+        }
+        assert_eq!(seen, vec![0]);
+        assert_eq!(
+            saved,
+            Checkpoint {
+                cursor: Some("1".to_string()),
+                pages: 1,
+                finished: false,
+                window: None,
+            }
+        );
+
+        let saved: Checkpoint =
+            serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+        let pages = paginate(saved, &fetch).collect::<Vec<_>>().await;
+        let last = pages.last().unwrap().as_ref().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(last.items, vec![2]);
+        assert_eq!(last.checkpoint.pages, 3);
+        assert!(last.checkpoint.finished);
+
+        // nothing left to fetch
+        let done = paginate(last.checkpoint.clone(), &fetch)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(done.is_empty());
+    });
+}
+
+#[test]
+fn limit_pages_and_items() {
+    crate::scrape::transport::block_on(async {
+        let fetch = |cursor: Option<String>| async move {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            Ok(Page::new(
+                vec![page * 10, page * 10 + 1, page * 10 + 2],
+                Some((page + 1).to_string()),
+            ))
+        };
+
+        let all: Vec<u32> = items(limit(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+            Some(2),
+            None,
+        ))
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(all, vec![0, 1, 2, 10, 11, 12]);
+
+        let pages = limit(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+            None,
+            Some(4),
+        )
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1].items, vec![10]);
+        // the cut page resumes from where it started
+        assert_eq!(pages[1].checkpoint.cursor.as_deref(), Some("1"));
+        assert_eq!(pages[1].checkpoint.pages, 1);
+    });
+}
+
+#[test]
+fn limit_stops_requesting_at_the_limit() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let url = "https://twitter.com/i/api/pages?cursor=";
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(format!("{url}0"), r#"{"items":[0,1,2],"next":"1"}"#)
+                .with_json(format!("{url}1"), r#"{"items":[3,4,5],"next":"2"}"#)
+                .with_json(format!("{url}2"), r#"{"items":[6,7,8],"next":"3"}"#),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        #[derive(Deserialize)]
+        struct Body {
+            items: Vec<u32>,
+            next: String,
+        }
+        let fetch = |cursor: Option<String>| {
+            let scraper = &scraper;
+            async move {
+                let cursor = cursor.unwrap_or_else(|| "0".to_string());
+                let body: Body = scraper
+                    .api_req(scraper.make_get_req(format!("{url}{cursor}")))
+                    .await?;
+                Ok(Page::new(body.items, Some(body.next)))
+            }
+        };
+
+        let pages = limit(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+            Some(2),
+            None,
+        )
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(transport.request_count(url), 2);
+
+        // four items are done halfway through the second page
+        let all: Vec<u32> = items(limit(
+            paginate(Checkpoint::default(), fetch),
+            Checkpoint::default(),
+            None,
+            Some(4),
+        ))
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+        assert_eq!(transport.request_count(url), 4);
+    });
+}
+
+#[test]
+fn resume_after_limit_loses_nothing() {
+    crate::scrape::transport::block_on(async {
+        let fetch = |cursor: Option<String>| async move {
+            let page = cursor.map_or(0, |c| c.parse::<u32>().unwrap());
+            Ok(Page::new(
+                vec![page * 10, page * 10 + 1, page * 10 + 2],
+                (page < 2).then(|| (page + 1).to_string()),
+            ))
+        };
+
+        for max in 1..9 {
+            let partial = collect_partial(
+                limit(
+                    paginate(Checkpoint::default(), fetch),
+                    Checkpoint::default(),
+                    None,
+                    Some(max),
+                ),
+                Checkpoint::default(),
+            )
+            .await;
+            assert_eq!(partial.data.len(), max);
+
+            // the cut page comes again, nothing after what was kept is skipped
+            let mut all = partial.data;
+            let rest: Vec<u32> = items(paginate(partial.checkpoint, fetch))
+                .try_collect()
+                .await
+                .unwrap();
+            all.extend(rest);
+            all.sort_unstable();
+            all.dedup();
+            assert_eq!(all, vec![0, 1, 2, 10, 11, 12, 20, 21, 22]);
+        }
+    });
+}
@@ -0,0 +1,217 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::error::{SResult, TwtScrapeError};
+use crate::scrape::transport::{ApiResponse, Transport};
+
+// One route per proxy (or a single direct route). Guest tokens stay on the route that activated them.
+// Routes that time out or keep getting 429s are quarantined for a while and skipped.
+#[derive(Debug)]
+pub struct ProxyPool {
+    routes: Vec<Route>,
+    next: AtomicUsize,
+    quarantine_after: u32,
+    quarantine_for: Duration,
+}
+
+#[derive(Debug)]
+struct Route {
+    name: String,
+    transport: Arc<dyn Transport>,
+    health: Mutex<RouteHealth>,
+}
+
+#[derive(Debug, Default)]
+struct RouteHealth {
+    requests: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyStats {
+    pub name: String,
+    pub requests: u64,
+    pub failures: u64,
+    pub quarantined_for: Option<Duration>,
+}
+
+impl ProxyPool {
+    pub fn new(quarantine_after: u32, quarantine_for: Duration) -> Self {
+        ProxyPool {
+            routes: vec![],
+            next: AtomicUsize::new(0),
+            quarantine_after: quarantine_after.max(1),
+            quarantine_for,
+        }
+    }
+
+    pub fn single(transport: Arc<dyn Transport>) -> Self {
+        let mut pool = Self::default();
+        pool.push("direct", transport);
+        pool
+    }
+
+    pub fn push(&mut self, name: impl Into<String>, transport: Arc<dyn Transport>) {
+        self.routes.push(Route {
+            name: name.into(),
+            transport,
+            health: Mutex::default(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    pub fn name(&self, route: usize) -> &str {
+        &self.routes[route].name
+    }
+
+    pub fn transport(&self, route: usize) -> &Arc<dyn Transport> {
+        &self.routes[route].transport
+    }
+
+    pub fn is_healthy(&self, route: usize) -> bool {
+        let mut health = self.routes[route].health.lock().unwrap();
+        match health.quarantined_until {
+            Some(until) if until > Instant::now() => false,
+            Some(_) => {
+                health.quarantined_until = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    // with every route quarantined, using a bad one beats waiting forever
+    pub fn is_usable(&self, route: usize) -> bool {
+        self.is_healthy(route) || !(0..self.len()).any(|route| self.is_healthy(route))
+    }
+
+    // round robin over the usable routes
+    pub fn next_route(&self) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.len())
+            .map(|offset| (start + offset) % self.len())
+            .find(|route| self.is_usable(*route))
+            .unwrap_or(start % self.len())
+    }
+
+    pub fn report(&self, route: usize, outcome: &SResult<ApiResponse>) {
+        // a 403 is about the guest token or account (code 239 and friends), not the proxy
+        let failed = match outcome {
+            Ok(response) => response.status == 429,
+            Err(why) => matches!(why, TwtScrapeError::RequestFailed(_)),
+        };
+
+        let route = &self.routes[route];
+        let mut health = route.health.lock().unwrap();
+        health.requests += 1;
+        // a route only gets traffic while quarantined through health checks or the fallback,
+        // so a success means it works again
+        if !failed {
+            health.consecutive_failures = 0;
+            health.quarantined_until = None;
+            return;
+        }
+
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.quarantine_after {
+            warn!(
+                proxy = route.name,
+                "Quarantining proxy after repeated failures."
+            );
+            health.consecutive_failures = 0;
+            health.quarantined_until = Some(Instant::now() + self.quarantine_for);
+        }
+    }
+
+    pub fn stats(&self) -> Vec<ProxyStats> {
+        let now = Instant::now();
+        self.routes
+            .iter()
+            .map(|route| {
+                let health = route.health.lock().unwrap();
+                ProxyStats {
+                    name: route.name.clone(),
+                    requests: health.requests,
+                    failures: health.failures,
+                    quarantined_for: health
+                        .quarantined_until
+                        .filter(|until| *until > now)
+                        .map(|until| until - now),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ProxyPool {
+    fn default() -> Self {
+        Self::new(3, Duration::from_secs(60 * 5))
+    }
+}
+
+#[test]
+fn quarantine_failing_route() {
+    use crate::scrape::transport::FixtureTransport;
+
+    let mut pool = ProxyPool::new(2, Duration::from_secs(60));
+    pool.push("a", Arc::new(FixtureTransport::new()));
+    pool.push("b", Arc::new(FixtureTransport::new()));
+
+    let limited = Ok(ApiResponse::new("https://twitter.com", 429, ""));
+    let fine = Ok(ApiResponse::new("https://twitter.com", 200, ""));
+
+    pool.report(0, &limited);
+    pool.report(0, &fine);
+    pool.report(0, &limited);
+    assert!(pool.is_healthy(0));
+
+    pool.report(0, &limited);
+    assert!(!pool.is_healthy(0));
+    for _ in 0..4 {
+        assert_eq!(pool.next_route(), 1);
+    }
+
+    pool.report(1, &limited);
+    pool.report(1, &limited);
+    assert!(pool.is_usable(0) && pool.is_usable(1));
+
+    pool.report(0, &fine);
+    assert!(pool.is_healthy(0) && !pool.is_healthy(1));
+
+    let stats = pool.stats();
+    assert_eq!((stats[0].requests, stats[0].failures), (5, 3));
+}
+
+#[test]
+fn bad_guest_token_is_not_the_proxys_fault() {
+    use crate::scrape::transport::FixtureTransport;
+
+    let mut pool = ProxyPool::new(1, Duration::from_secs(60));
+    pool.push("a", Arc::new(FixtureTransport::new()));
+
+    let bad_token = Ok(ApiResponse::new(
+        "https://twitter.com",
+        403,
+        r#"{"errors":[{"code":239,"message":"Bad guest token."}]}"#,
+    ));
+    pool.report(0, &bad_token);
+    pool.report(0, &bad_token);
+    assert!(pool.is_healthy(0));
+    assert_eq!(pool.stats()[0].failures, 0);
+
+    pool.report(0, &Ok(ApiResponse::new("https://twitter.com", 429, "")));
+    assert!(!pool.is_healthy(0));
+}
@@ -0,0 +1,114 @@
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::scrape::transport::ApiResponse;
+
+// Twitter reports the budget for the endpoint that was hit on every response.
+// Graphql endpoints are keyed by operation name (`TweetDetail`), everything else by url path.
+// Only a logged in session waits on these, each guest token has a budget of its own and is
+// parked in the `TokenPool` when it's spent.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    // unix timestamp in seconds
+    pub reset: u64,
+}
+
+impl RateLimit {
+    pub fn from_response(response: &ApiResponse) -> Option<Self> {
+        Some(RateLimit {
+            limit: response.header("x-rate-limit-limit")?.parse().ok()?,
+            remaining: response.header("x-rate-limit-remaining")?.parse().ok()?,
+            reset: response.header("x-rate-limit-reset")?.parse().ok()?,
+        })
+    }
+
+    pub fn reset_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.reset)
+    }
+
+    // how long until the window resets, if it hasn't already
+    pub fn time_until_reset(&self) -> Option<Duration> {
+        self.reset_time().duration_since(SystemTime::now()).ok()
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0 && self.time_until_reset().is_some()
+    }
+}
+
+pub fn endpoint_of(url: &str) -> String {
+    let path = url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let path = path.find('/').map(|at| &path[at..]).unwrap_or("/");
+
+    match path.strip_prefix("/i/api/graphql/") {
+        Some(graphql) => graphql.rsplit('/').next().unwrap_or(graphql).to_string(),
+        None => path.to_string(),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimits {
+    endpoints: Mutex<HashMap<String, RateLimit>>,
+}
+
+impl RateLimits {
+    pub fn update(&self, endpoint: impl Into<String>, limit: RateLimit) {
+        self.endpoints
+            .lock()
+            .unwrap()
+            .insert(endpoint.into(), limit);
+    }
+
+    pub fn get(&self, endpoint: &str) -> Option<RateLimit> {
+        self.endpoints.lock().unwrap().get(endpoint).copied()
+    }
+
+    pub fn all(&self) -> HashMap<String, RateLimit> {
+        self.endpoints.lock().unwrap().clone()
+    }
+
+    // Some(wait) if the endpoint's budget is used up and the window hasn't reset yet
+    pub fn wait_time(&self, endpoint: &str) -> Option<Duration> {
+        self.get(endpoint)
+            .filter(RateLimit::is_exhausted)
+            .and_then(|limit| limit.time_until_reset())
+    }
+}
+
+#[test]
+fn parse_rate_limit_headers() {
+    let reset = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 60;
+    let response = ApiResponse::new("https://twitter.com/i/api/graphql/abc/Following", 200, "")
+        .with_header("x-rate-limit-limit", "500")
+        .with_header("X-Rate-Limit-Remaining", "0")
+        .with_header("x-rate-limit-reset", reset.to_string());
+
+    let limit = RateLimit::from_response(&response).unwrap();
+    assert_eq!((limit.limit, limit.remaining, limit.reset), (500, 0, reset));
+
+    let limits = RateLimits::default();
+    let endpoint = endpoint_of(&response.url);
+    assert_eq!(endpoint, "Following");
+    limits.update(endpoint, limit);
+    assert!(limits.wait_time("Following").unwrap() > Duration::from_secs(50));
+    assert_eq!(limits.wait_time("Followers"), None);
+
+    assert_eq!(
+        endpoint_of("https://api.twitter.com/1.1/guest/activate.json"),
+        "/1.1/guest/activate.json"
+    );
+}
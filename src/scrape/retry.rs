@@ -0,0 +1,118 @@
+use nanorand::{Rng, WyRand};
+use std::time::Duration;
+
+use crate::error::TwtScrapeError;
+
+// Decides which failed requests are tried again and how long to back off in between.
+// Status errors are matched against `retry_statuses`, every other error goes through `retry_error`.
+// A response saying the guest token was invalidated is always retried on a fresh token.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Duration,
+    pub retry_statuses: Vec<u16>,
+    pub retry_error: fn(&TwtScrapeError) -> bool,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // never retry, the first error is returned as is
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_retry_statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_statuses = statuses;
+        self
+    }
+
+    pub fn with_retry_error(mut self, retryable: fn(&TwtScrapeError) -> bool) -> Self {
+        self.retry_error = retryable;
+        self
+    }
+
+    pub fn is_retryable(&self, error: &TwtScrapeError) -> bool {
+        match error {
+            TwtScrapeError::ErrRequestStatus(status, _) => self.retry_statuses.contains(status),
+            other => (self.retry_error)(other),
+        }
+    }
+
+    // backoff before retry number `retry` (starting at 0)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let jitter = match self.jitter.as_millis() as u64 {
+            0 => Duration::ZERO,
+            jitter => Duration::from_millis(WyRand::new().generate_range(0..jitter)),
+        };
+        backoff + jitter
+    }
+}
+
+// connection failures and timeouts
+pub fn is_transient(error: &TwtScrapeError) -> bool {
+    matches!(
+        error,
+        TwtScrapeError::RequestFailed(_) | TwtScrapeError::LoadJsonFailed(_)
+    )
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: Duration::from_millis(250),
+            retry_statuses: vec![429, 500, 502, 503, 504],
+            retry_error: is_transient,
+        }
+    }
+}
+
+#[test]
+fn retry_policy_backoff_and_classification() {
+    let policy = RetryPolicy::new()
+        .with_base_delay(Duration::from_secs(1))
+        .with_max_delay(Duration::from_secs(5))
+        .with_jitter(Duration::ZERO);
+
+    let delays = (0..4).map(|n| policy.delay(n)).collect::<Vec<_>>();
+    assert_eq!(delays, [1, 2, 4, 5].map(Duration::from_secs).to_vec());
+
+    assert!(policy.is_retryable(&TwtScrapeError::ErrRequestStatus(503, String::new())));
+    assert!(!policy.is_retryable(&TwtScrapeError::ErrRequestStatus(404, String::new())));
+    assert!(!policy.is_retryable(&TwtScrapeError::ErrRequestStatus(403, String::new())));
+    assert!(!policy.is_retryable(&TwtScrapeError::SchemaAccessErr));
+}
@@ -0,0 +1,74 @@
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::SResult;
+use crate::error::TwtScrapeError::SessionError;
+use crate::scrape::rate_limit::RateLimit;
+use crate::scrape::AuthSession;
+
+// Everything a scraper learned about its identity, so a restarted process can pick up
+// where the last one left off instead of activating new guest tokens.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub bearer_token: String,
+    pub auth: Option<AuthSession>,
+    pub guest_tokens: Vec<SavedGuestToken>,
+    // name, value pairs sent to twitter.com
+    pub cookies: Vec<(String, String)>,
+    pub rate_limits: HashMap<String, RateLimit>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGuestToken {
+    pub token: String,
+    // unix timestamp in seconds
+    pub activated: u64,
+    // the proxy that activated it, `None` for sessions saved before proxies were tracked
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+impl SessionState {
+    pub async fn load(path: impl AsRef<Path>) -> SResult<Self> {
+        let path = path.as_ref();
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|why| SessionError(format!("{}: {why}", path.display())))?;
+        serde_json::from_slice(&data)
+            .map_err(|why| SessionError(format!("{}: {why}", path.display())))
+    }
+
+    // holds the auth token, so on unix only the owner can read it
+    pub async fn save(&self, path: impl AsRef<Path>) -> SResult<()> {
+        let path = path.as_ref();
+        let data = serde_json::to_vec_pretty(self).map_err(|why| SessionError(why.to_string()))?;
+        let write = async {
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            let mut file = options.open(path).await?;
+            // the mode only applies to new files, an older save may be world readable
+            #[cfg(unix)]
+            file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))
+                .await?;
+            file.write_all(&data).await?;
+            file.flush().await
+        };
+        write
+            .await
+            .map_err(|why| SessionError(format!("{}: {why}", path.display())))
+    }
+}
+
+pub(crate) fn parse_cookie_header(header: &str) -> Vec<(String, String)> {
+    header
+        .split("; ")
+        .filter_map(|cookie| {
+            let (name, value) = cookie.split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
@@ -1,72 +1,202 @@
 use nanorand::{Rng, WyRand};
-use std::env::var;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::{future::Future, time::Duration};
 use tokio::{
     join,
     sync::Mutex,
-    time::{sleep, Instant},
+    time::{sleep, sleep_until, Instant},
 };
+use tracing::warn;
 
 // The parts of the scraper that evolve with time
 
 //std mutex guard can't carry accross awaits
 //std mutex is much faster!
 //but we need to stop multiple processes from acquiring the token
+//
+// Requests are spread round robin over the pool. A token that gets rate limited is parked
+// until its reset time, and one that twitter invalidates is reactivated on next use.
 #[derive(Debug)]
-pub struct TimedToken(Mutex<TimeToken>);
-impl TimedToken {
-    pub fn new() -> Self {
-        TimedToken(Mutex::new(TimeToken::dud()))
+pub struct TokenPool {
+    slots: Vec<Mutex<TimeToken>>,
+    next: AtomicUsize,
+}
+impl TokenPool {
+    pub fn new(size: usize, expiration: Duration) -> Self {
+        TokenPool {
+            slots: (0..size.max(1))
+                .map(|_| Mutex::new(TimeToken::dud(expiration)))
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
     }
+
     #[tracing::instrument]
-    pub async fn init(&self, expiration: Duration, token: String) {
-        let mut guard = self.0.lock().await;
+    pub async fn init(&self, slot: usize, token: String) {
+        let mut guard = self.slots[slot].lock().await;
+        let expiration = guard.expiration;
         *guard = TimeToken::new(expiration, token);
     }
+
     // We're doing io. The extra allocation absolutely isn't a bottleneck
+    // `usable` skips slots whose proxy is quarantined, `refresh` activates a token for a slot
+    #[tracing::instrument(skip(usable, refresh))]
+    pub async fn acquire<E, U, F, Fut>(&self, usable: U, refresh: F) -> Result<(usize, String), E>
+    where
+        U: Fn(usize) -> bool,
+        F: Fn(usize) -> Fut,
+        Fut: Future<Output = Result<String, E>>,
+    {
+        loop {
+            let start = self.next.fetch_add(1, Ordering::Relaxed);
+            let now = Instant::now();
+            let mut earliest_unpark = None;
+            // nothing usable at all, fall back to every slot rather than spinning
+            let any_usable = (0..self.slots.len()).any(&usable);
+
+            for offset in 0..self.slots.len() {
+                let slot = (start + offset) % self.slots.len();
+                if any_usable && !usable(slot) {
+                    continue;
+                }
+                let mut guard = self.slots[slot].lock().await;
+
+                if let Some(until) = guard.parked_until {
+                    if until > now {
+                        earliest_unpark =
+                            Some(earliest_unpark.map_or(until, |e: Instant| e.min(until)));
+                        continue;
+                    }
+                    guard.parked_until = None;
+                }
+
+                let token = guard.get_token(|| refresh(slot)).await?.to_string();
+                guard.requests += 1;
+                return Ok((slot, token));
+            }
+
+            if let Some(until) = earliest_unpark {
+                warn!("Every guest token is rate limited, waiting for the first reset.");
+                sleep_until(until).await;
+            }
+        }
+    }
+
+    #[tracing::instrument]
+    pub async fn park(&self, slot: usize, until: Instant) {
+        let mut guard = self.slots[slot].lock().await;
+        guard.rate_limited += 1;
+        guard.parked_until = Some(guard.parked_until.map_or(until, |p| p.max(until)));
+    }
+
+    // the token is replaced the next time the slot is used
+    #[tracing::instrument]
+    pub async fn invalidate(&self, slot: usize) {
+        let mut guard = self.slots[slot].lock().await;
+        guard.invalidated = true;
+    }
+
+    // every token that is still usable, its slot and how long ago it was activated
+    pub async fn live_tokens(&self) -> Vec<(usize, String, Duration)> {
+        let now = Instant::now();
+        let mut tokens = Vec::with_capacity(self.slots.len());
+        for (index, slot) in self.slots.iter().enumerate() {
+            let guard = slot.lock().await;
+            let age = now.duration_since(guard.creation);
+            if !guard.invalidated && age < guard.expiration {
+                tokens.push((index, guard.token.clone(), age));
+            }
+        }
+        tokens
+    }
+
+    // puts back a token that was activated `age` ago, false if it's too old to use
     #[tracing::instrument]
-    pub async fn get_token<E>(
-        &self,
-        refresh: impl Future<Output = Result<String, E>>,
-    ) -> Result<String, E> {
-        let mut guard = self.0.lock().await;
-        guard.get_token(refresh).await.map(|s| String::from(s))
+    pub async fn restore(&self, slot: usize, token: String, age: Duration) -> bool {
+        let mut guard = self.slots[slot].lock().await;
+        let creation = match Instant::now().checked_sub(age) {
+            Some(creation) if age < guard.expiration => creation,
+            _ => return false,
+        };
+        let expiration = guard.expiration;
+        *guard = TimeToken {
+            creation,
+            ..TimeToken::new(expiration, token)
+        };
+        true
+    }
+
+    pub async fn stats(&self) -> Vec<GuestTokenStats> {
+        let now = Instant::now();
+        let mut stats = Vec::with_capacity(self.slots.len());
+        for slot in &self.slots {
+            let guard = slot.lock().await;
+            stats.push(GuestTokenStats {
+                age: now.duration_since(guard.creation),
+                requests: guard.requests,
+                rate_limited: guard.rate_limited,
+                parked_for: guard
+                    .parked_until
+                    .filter(|until| *until > now)
+                    .map(|until| until - now),
+            });
+        }
+        stats
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestTokenStats {
+    pub age: Duration,
+    pub requests: u64,
+    pub rate_limited: u64,
+    pub parked_for: Option<Duration>,
+}
+
 #[derive(Debug, Clone)]
 struct TimeToken {
     token: String,
     creation: Instant,
     expiration: Duration,
+    invalidated: bool,
+    parked_until: Option<Instant>,
+    requests: u64,
+    rate_limited: u64,
 }
 impl TimeToken {
-    fn dud() -> TimeToken {
-        Self::new(Duration::ZERO, String::new())
+    fn dud(expiration: Duration) -> TimeToken {
+        TimeToken {
+            invalidated: true,
+            ..Self::new(expiration, String::new())
+        }
     }
     fn new(expiration: Duration, token: String) -> TimeToken {
         TimeToken {
             token,
             creation: Instant::now(),
             expiration,
+            invalidated: false,
+            parked_until: None,
+            requests: 0,
+            rate_limited: 0,
         }
     }
 
-    #[tracing::instrument]
-    async fn get_token<E>(
-        &mut self,
-        refresh: impl Future<Output = Result<String, E>>,
-    ) -> Result<&str, E> {
+    async fn get_token<E, F, Fut>(&mut self, refresh: F) -> Result<&str, E>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<String, E>>,
+    {
         let now = Instant::now();
 
-        if now.duration_since(self.creation) > self.expiration {
-            let ntk = refresh.await?;
-            *self = TimeToken {
-                token: ntk,
-                creation: now,
-                expiration: self.expiration,
-            };
+        if self.invalidated || now.duration_since(self.creation) > self.expiration {
+            let ntk = refresh().await?;
+            *self = TimeToken::new(self.expiration, ntk);
         }
 
         Ok(&self.token)
@@ -107,16 +237,51 @@ impl Delayer {
 
         let current_time = Instant::now();
         let diff = current_time.duration_since(*last_time);
+        let delay = Duration::from_millis(self.delay);
 
-        *last_time = current_time;
-
-        if diff <= self.delay {
-            let delay = Duration::from_millis(self.delay);
-            let random = Duration::from_millis(rng.generate_range(0..self.variation));
+        // the locks are held while sleeping, so tasks sharing a scraper queue up here
+        if diff <= delay {
+            let random = match self.variation {
+                0 => Duration::ZERO,
+                variation => Duration::from_millis(rng.generate_range(0..variation)),
+            };
             sleep((delay - diff) + random).await;
         }
+
+        *last_time = Instant::now();
     }
     //async fn wait(time: Duration) {
     //    sleep(self.delay - diff).await;
     //}
 }
+
+#[test]
+fn token_pool_rotates_and_parks() {
+    crate::scrape::transport::block_on(async {
+        let activations = std::sync::atomic::AtomicUsize::new(0);
+        let refresh = |_| async {
+            let n = activations.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, ()>(format!("token{n}"))
+        };
+
+        let pool = TokenPool::new(2, Duration::from_secs(60));
+        let (first, _) = pool.acquire(|_| true, refresh).await.unwrap();
+        let (second, _) = pool.acquire(|_| true, refresh).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(activations.load(Ordering::Relaxed), 2);
+
+        pool.park(first, Instant::now() + Duration::from_secs(60))
+            .await;
+        for _ in 0..3 {
+            assert_eq!(pool.acquire(|_| true, refresh).await.unwrap().0, second);
+        }
+
+        pool.invalidate(second).await;
+        let (slot, token) = pool.acquire(|_| true, refresh).await.unwrap();
+        assert_eq!((slot, token.as_str()), (second, "token2"));
+
+        let stats = pool.stats().await;
+        assert_eq!(stats[first].rate_limited, 1);
+        assert!(stats[first].parked_for.is_some());
+    });
+}
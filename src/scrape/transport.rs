@@ -0,0 +1,305 @@
+use ahash::HashMap;
+use reqwest::{Client, Method};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use crate::error::SResult;
+use crate::error::TwtScrapeError::{
+    ErrRequestStatus, LoadJsonFailed, MissingFixture, RequestFailed, SchemaErr,
+};
+
+// The wire the scraper talks through.
+// `ReqwestTransport` is the real network, `FixtureTransport` serves canned responses
+// so the parsers can be run on a machine without network access.
+
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = SResult<ApiResponse>> + Send + 'a>>;
+
+pub trait Transport: Debug + Send + Sync {
+    fn execute(&self, request: ApiRequest) -> TransportFuture<'_>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl ApiRequest {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        ApiRequest {
+            method,
+            url: url.into(),
+            headers: vec![],
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn bearer_auth(self, token: impl AsRef<str>) -> Self {
+        self.header("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiResponse {
+    pub status: u16,
+    // the final url, after any redirects were followed
+    pub url: String,
+    // header names are always lowercase
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    pub fn new(url: impl Into<String>, status: u16, body: impl Into<Vec<u8>>) -> Self {
+        ApiResponse {
+            status,
+            url: url.into(),
+            headers: HashMap::default(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header(mut self, key: impl AsRef<str>, value: impl Into<String>) -> Self {
+        self.headers
+            .insert(key.as_ref().to_ascii_lowercase(), value.into());
+        self
+    }
+
+    pub fn header(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.headers
+            .get(&key.as_ref().to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn error_for_status(self) -> SResult<Self> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(ErrRequestStatus(self.status, self.url))
+        }
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> SResult<T> {
+        serde_json::from_slice(&self.body).map_err(SchemaErr)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn execute(&self, request: ApiRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let mut builder = self.client.request(request.method, &request.url);
+            for (key, value) in &request.headers {
+                builder = builder.header(key, value);
+            }
+
+            let response = builder.send().await.map_err(RequestFailed)?;
+
+            let status = response.status().as_u16();
+            let url = response.url().to_string();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+                .collect();
+            let body = response.bytes().await.map_err(LoadJsonFailed)?.to_vec();
+
+            Ok(ApiResponse {
+                status,
+                url,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+// Responses are looked up by exact url first, then by the longest registered url prefix.
+// Each url answers with its queued responses in order; the last one is repeated forever.
+// Every requested url is logged, so tests can check how many requests were made.
+#[derive(Debug, Default)]
+pub struct FixtureTransport {
+    responses: Mutex<HashMap<String, VecDeque<ApiResponse>>>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl FixtureTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_response(self, url: impl Into<String>, response: ApiResponse) -> Self {
+        self.push_response(url, response);
+        self
+    }
+
+    pub fn with_json(self, url: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        let url = url.into();
+        let response = ApiResponse::new(url.clone(), 200, body)
+            .with_header("content-type", "application/json");
+        self.with_response(url, response)
+    }
+
+    // answers guest activation so `ScraperBuilder::finish` can complete offline
+    pub fn with_guest_token(self, token: impl AsRef<str>) -> Self {
+        self.with_json(
+            "https://api.twitter.com/1.1/guest/activate.json",
+            format!("{{\"guest_token\":\"{}\"}}", token.as_ref()),
+        )
+    }
+
+    pub fn push_response(&self, url: impl Into<String>, response: ApiResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(url.into())
+            .or_default()
+            .push_back(response);
+    }
+
+    // every url requested so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // how many requests went to urls starting with `prefix`
+    pub fn request_count(&self, prefix: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|url| url.starts_with(prefix))
+            .count()
+    }
+
+    fn next_response(&self, url: &str) -> Option<ApiResponse> {
+        let mut responses = self.responses.lock().unwrap();
+
+        let key = if responses.contains_key(url) {
+            url.to_string()
+        } else {
+            responses
+                .keys()
+                .filter(|k| url.starts_with(k.as_str()))
+                .max_by_key(|k| k.len())?
+                .clone()
+        };
+
+        let queue = responses.get_mut(&key)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn execute(&self, request: ApiRequest) -> TransportFuture<'_> {
+        self.requests.lock().unwrap().push(request.url.clone());
+        let response = self
+            .next_response(&request.url)
+            .ok_or(MissingFixture(request.url));
+        Box::pin(async move { response })
+    }
+}
+
+// runs a test on a fresh single threaded runtime
+#[cfg(test)]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+// A scraper answering from `transport`, with guest activation already queued.
+// Keep a clone of the `Arc` to look at `requests` afterwards.
+#[cfg(test)]
+pub(crate) async fn fixture_scraper(
+    transport: &std::sync::Arc<FixtureTransport>,
+) -> crate::scrape::Scraper {
+    fixture_scraper_from(crate::scrape::ScraperBuilder::new(), transport).await
+}
+
+// like `fixture_scraper`, for tests that need other builder options
+#[cfg(test)]
+pub(crate) async fn fixture_scraper_from(
+    builder: crate::scrape::ScraperBuilder,
+    transport: &std::sync::Arc<FixtureTransport>,
+) -> crate::scrape::Scraper {
+    transport.push_response(
+        "https://api.twitter.com/1.1/guest/activate.json",
+        ApiResponse::new(
+            "https://api.twitter.com/1.1/guest/activate.json",
+            200,
+            "{\"guest_token\":\"1234\"}",
+        )
+        .with_header("content-type", "application/json"),
+    );
+    builder
+        .with_transport(transport.clone())
+        .finish()
+        .await
+        .unwrap()
+}
+
+#[test]
+fn fixture_transport_replays_in_order() {
+    block_on(async {
+        let transport = FixtureTransport::new()
+            .with_json("https://twitter.com/i/api/a", "1")
+            .with_json("https://twitter.com/i/api/a", "2")
+            .with_json("https://twitter.com/i/api/", "3");
+
+        let mut bodies = vec![];
+        for url in [
+            "https://twitter.com/i/api/a",
+            "https://twitter.com/i/api/a",
+            "https://twitter.com/i/api/a",
+            "https://twitter.com/i/api/b",
+        ] {
+            let response = transport.execute(ApiRequest::get(url)).await.unwrap();
+            bodies.push(response.json::<u32>().unwrap());
+        }
+        assert_eq!(bodies, vec![1, 2, 2, 3]);
+        assert_eq!(transport.request_count("https://twitter.com/i/api/a"), 3);
+
+        assert!(transport
+            .execute(ApiRequest::get("https://example.com"))
+            .await
+            .is_err());
+    });
+}
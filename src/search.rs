@@ -1,37 +1,80 @@
-use crate::error::SResult;
-use crate::error::TwtScrapeError::TwitterBadRestId;
+use crate::error::TwtScrapeError::{BadJSONSchema, SearchQueryError, TwitterBadRestId};
+use crate::error::{SResult, TwtScrapeError};
+#[cfg(feature = "scrape")]
+use crate::scrape::pagination::{
+    collect_partial, items, limit, paginate, Checkpoint, ItemError, Page, PartialResult,
+};
 #[cfg(feature = "scrape")]
 use crate::scrape::Scraper;
+use crate::timeline::GlobalObjects;
+use crate::tweet::Tweet;
+use crate::user::User;
+#[cfg(feature = "scrape")]
+use ahash::{HashSet, HashSetExt};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+#[cfg(feature = "scrape")]
+use futures::{future, stream, Stream, StreamExt, TryStreamExt};
 use rkyv::Archive;
 use serde::de::{MapAccess, Visitor};
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::fmt;
 use std::fmt::Display;
+use std::str::FromStr;
+#[cfg(feature = "scrape")]
+use tracing::warn;
 #[cfg(feature = "scrape")]
 use url::Url;
 
 #[cfg(feature = "scrape")]
 pub fn twitter_request_url_search(
     query: impl AsRef<str> + Display,
+    mode: SearchMode,
     cursor: Option<impl AsRef<str> + Display>,
 ) -> String {
     let mut url = Url::parse("https://twitter.com/i/api/2/search/adaptive.json").unwrap();
 
-    url.set_query(Some("count=20"));
-    url.set_query(Some("query_source=typed_query"));
-    url.set_query(Some("pc=1"));
-    url.set_query(Some("spelling_corrections=1"));
-    url.set_query(Some("tweet_search_mode=live"));
-    let query = urlencoding::encode(query.as_ref());
-    url.set_query(Some(&format!("q={query}")));
-    if let Some(c) = cursor {
-        let c = urlencoding::encode(c.as_ref());
-        url.set_query(Some(&format!("cursor={c}")))
+    {
+        let mut params = url.query_pairs_mut();
+        params
+            .append_pair("count", "20")
+            .append_pair("query_source", "typed_query")
+            .append_pair("pc", "1")
+            .append_pair("spelling_corrections", "1");
+        match mode {
+            SearchMode::Top => {}
+            SearchMode::Latest => {
+                params.append_pair("tweet_search_mode", "live");
+            }
+            SearchMode::People => {
+                params.append_pair("result_filter", "user");
+            }
+            SearchMode::Photos => {
+                params.append_pair("result_filter", "image");
+            }
+            SearchMode::Videos => {
+                params.append_pair("result_filter", "video");
+            }
+        }
+        params.append_pair("q", query.as_ref());
+        if let Some(c) = cursor {
+            params.append_pair("cursor", c.as_ref());
+        }
     }
 
     url.to_string()
 }
 
+// The tabs of the search page. People finds users, the rest find tweets.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchMode {
+    Top,
+    #[default]
+    Latest,
+    People,
+    Photos,
+    Videos,
+}
+
 #[derive(
     Clone,
     Debug,
@@ -46,107 +89,845 @@ pub fn twitter_request_url_search(
 )]
 pub struct Search {
     pub tweets: Vec<u64>,
+    // only filled in when the search hydrates
+    #[serde(default)]
+    pub hydrated: Vec<Tweet>,
+    // tweet authors, or the users found by a People search
+    #[serde(default)]
+    pub users: Vec<User>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    // parse full tweets and authors out of the response's globalObjects, on unless turned
+    // off with `without_hydration`. People searches always parse their users
+    pub hydrate: bool,
+    pub max_results: Option<usize>,
+    pub max_pages: Option<u64>,
+    // needs a query with both since: and until:
+    pub slice: Option<DateSlice>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            mode: SearchMode::default(),
+            hydrate: true,
+            max_results: None,
+            max_pages: None,
+            slice: None,
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // tweet ids only
+    pub fn without_hydration(mut self) -> Self {
+        self.hydrate = false;
+        self
+    }
+
+    pub fn with_max_results(mut self, max: usize) -> Self {
+        self.max_results = Some(max);
+        self
+    }
+
+    pub fn with_max_pages(mut self, max: u64) -> Self {
+        self.max_pages = Some(max);
+        self
+    }
+
+    pub fn with_date_slicing(mut self, slice: DateSlice) -> Self {
+        self.slice = Some(slice);
+        self
+    }
+}
+
+// Search only pages back so far into a single query. Cutting `since..until` into
+// windows and searching each one gets past that for busy queries.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DateSlice {
+    Day,
+    Hour,
+}
+
+impl DateSlice {
+    pub fn length(self) -> Duration {
+        match self {
+            DateSlice::Day => Duration::days(1),
+            DateSlice::Hour => Duration::hours(1),
+        }
+    }
+
+    // newest window first, the order search itself returns tweets in
+    pub fn windows(
+        self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut windows = vec![];
+        let mut end = until;
+        while end > since {
+            let start = (end - self.length()).max(since);
+            windows.push((start, end));
+            end = start;
+        }
+        windows
+    }
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
+pub enum SearchHit {
+    Tweet {
+        id: u64,
+        tweet: Option<Tweet>,
+        author: Option<User>,
+    },
+    User {
+        id: u64,
+        // None when the user couldn't be parsed
+        user: Option<User>,
+    },
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchFilter {
+    Media,
+    Images,
+    NativeVideo,
+    Videos,
+    Links,
+    Replies,
+    Retweets,
+    NativeRetweets,
+    Quote,
+    Verified,
+    BlueVerified,
+    Safe,
+    News,
+    Other(String),
+}
+
+impl SearchFilter {
+    pub fn name(&self) -> &str {
+        match self {
+            SearchFilter::Media => "media",
+            SearchFilter::Images => "images",
+            SearchFilter::NativeVideo => "native_video",
+            SearchFilter::Videos => "videos",
+            SearchFilter::Links => "links",
+            SearchFilter::Replies => "replies",
+            SearchFilter::Retweets => "retweets",
+            SearchFilter::NativeRetweets => "nativeretweets",
+            SearchFilter::Quote => "quote",
+            SearchFilter::Verified => "verified",
+            SearchFilter::BlueVerified => "blue_verified",
+            SearchFilter::Safe => "safe",
+            SearchFilter::News => "news",
+            SearchFilter::Other(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "media" => SearchFilter::Media,
+            "images" => SearchFilter::Images,
+            "native_video" => SearchFilter::NativeVideo,
+            "videos" => SearchFilter::Videos,
+            "links" => SearchFilter::Links,
+            "replies" => SearchFilter::Replies,
+            "retweets" => SearchFilter::Retweets,
+            "nativeretweets" => SearchFilter::NativeRetweets,
+            "quote" => SearchFilter::Quote,
+            "verified" => SearchFilter::Verified,
+            "blue_verified" => SearchFilter::BlueVerified,
+            "safe" => SearchFilter::Safe,
+            "news" => SearchFilter::News,
+            other => SearchFilter::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Distance {
+    Kilometers(f64),
+    Miles(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Geocode {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius: Distance,
+}
+
+impl Display for Geocode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (radius, unit) = match self.radius {
+            Distance::Kilometers(km) => (km, "km"),
+            Distance::Miles(mi) => (mi, "mi"),
+        };
+        write!(f, "{},{},{radius}{unit}", self.latitude, self.longitude)
+    }
+}
+
+impl FromStr for Geocode {
+    type Err = TwtScrapeError;
+
+    // `37.78,-122.41,10km`
+    fn from_str(s: &str) -> SResult<Self> {
+        let bad = || SearchQueryError(format!("Bad geocode `{s}`"));
+        let mut parts = s.split(',');
+        let (lat, lon, radius) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(lat), Some(lon), Some(radius), None) => (lat, lon, radius),
+            _ => return Err(bad()),
+        };
+        let radius = if let Some(km) = radius.strip_suffix("km") {
+            Distance::Kilometers(km.parse().map_err(|_| bad())?)
+        } else if let Some(mi) = radius.strip_suffix("mi") {
+            Distance::Miles(mi.parse().map_err(|_| bad())?)
+        } else {
+            return Err(bad());
+        };
+
+        Ok(Geocode {
+            latitude: lat.parse().map_err(|_| bad())?,
+            longitude: lon.parse().map_err(|_| bad())?,
+            radius,
+        })
+    }
+}
+
+// Twitter's advanced search operators. Renders to the query string the search box would
+// send, and parses one back.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    // words, "quoted phrases" and any operator not modelled here, as typed
+    pub terms: Vec<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub min_faves: Option<u32>,
+    pub min_retweets: Option<u32>,
+    pub min_replies: Option<u32>,
+    pub filters: Vec<SearchFilter>,
+    pub excluded_filters: Vec<SearchFilter>,
+    pub lang: Option<String>,
+    pub conversation_id: Option<u64>,
+    pub geocode: Option<Geocode>,
+}
+
+// whole days render as `2023-01-31`, anything else as `2023-01-31_13:00:00_UTC`
+const SEARCH_DATE: &str = "%Y-%m-%d";
+const SEARCH_DATETIME: &str = "%Y-%m-%d_%H:%M:%S_UTC";
+
+fn format_search_date(date: &DateTime<Utc>) -> String {
+    if date.time() == NaiveTime::MIN {
+        date.format(SEARCH_DATE).to_string()
+    } else {
+        date.format(SEARCH_DATETIME).to_string()
+    }
+}
+
+fn parse_search_date(date: &str) -> SResult<DateTime<Utc>> {
+    NaiveDate::parse_from_str(date, SEARCH_DATE)
+        .map(|day| day.and_time(NaiveTime::MIN))
+        .or_else(|_| NaiveDateTime::parse_from_str(date, SEARCH_DATETIME))
+        .map(|naive| Utc.from_utc_datetime(&naive))
+        .map_err(|why| SearchQueryError(format!("Bad date `{date}`: {why}")))
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.terms.push(text.into());
+        self
+    }
+
+    pub fn with_from(mut self, handle: impl AsRef<str>) -> Self {
+        self.from = Some(handle.as_ref().trim_start_matches('@').to_string());
+        self
+    }
+
+    pub fn with_to(mut self, handle: impl AsRef<str>) -> Self {
+        self.to = Some(handle.as_ref().trim_start_matches('@').to_string());
+        self
+    }
+
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn with_min_faves(mut self, faves: u32) -> Self {
+        self.min_faves = Some(faves);
+        self
+    }
+
+    pub fn with_min_retweets(mut self, retweets: u32) -> Self {
+        self.min_retweets = Some(retweets);
+        self
+    }
+
+    pub fn with_min_replies(mut self, replies: u32) -> Self {
+        self.min_replies = Some(replies);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: SearchFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn without_filter(mut self, filter: SearchFilter) -> Self {
+        self.excluded_filters.push(filter);
+        self
+    }
+
+    pub fn with_lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    pub fn with_conversation_id(mut self, id: u64) -> Self {
+        self.conversation_id = Some(id);
+        self
+    }
+
+    pub fn with_geocode(mut self, latitude: f64, longitude: f64, radius: Distance) -> Self {
+        self.geocode = Some(Geocode {
+            latitude,
+            longitude,
+            radius,
+        });
+        self
+    }
+
+    // one query per date window, newest first
+    pub fn slices(&self, slice: DateSlice) -> SResult<Vec<SearchQuery>> {
+        let (since, until) = match (self.since, self.until) {
+            (Some(since), Some(until)) => (since, until),
+            _ => {
+                return Err(SearchQueryError(
+                    "Date slicing needs both since: and until:".to_string(),
+                ))
+            }
+        };
+
+        Ok(slice
+            .windows(since, until)
+            .into_iter()
+            .map(|(since, until)| SearchQuery {
+                since: Some(since),
+                until: Some(until),
+                ..self.clone()
+            })
+            .collect())
+    }
+}
+
+impl Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = self.terms.clone();
+        if let Some(from) = &self.from {
+            parts.push(format!("from:{from}"));
+        }
+        if let Some(to) = &self.to {
+            parts.push(format!("to:{to}"));
+        }
+        if let Some(id) = self.conversation_id {
+            parts.push(format!("conversation_id:{id}"));
+        }
+        if let Some(lang) = &self.lang {
+            parts.push(format!("lang:{lang}"));
+        }
+        if let Some(since) = &self.since {
+            parts.push(format!("since:{}", format_search_date(since)));
+        }
+        if let Some(until) = &self.until {
+            parts.push(format!("until:{}", format_search_date(until)));
+        }
+        if let Some(faves) = self.min_faves {
+            parts.push(format!("min_faves:{faves}"));
+        }
+        if let Some(retweets) = self.min_retweets {
+            parts.push(format!("min_retweets:{retweets}"));
+        }
+        if let Some(replies) = self.min_replies {
+            parts.push(format!("min_replies:{replies}"));
+        }
+        for filter in &self.filters {
+            parts.push(format!("filter:{}", filter.name()));
+        }
+        for filter in &self.excluded_filters {
+            parts.push(format!("-filter:{}", filter.name()));
+        }
+        if let Some(geocode) = &self.geocode {
+            parts.push(format!("geocode:{geocode}"));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+// splits on spaces, keeping "quoted phrases" whole
+fn query_tokens(query: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut quoted = false;
+    for (at, c) in query.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(from) = start.take() {
+                    tokens.push(&query[from..at]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(at);
+    }
+    if let Some(from) = start {
+        tokens.push(&query[from..]);
+    }
+    tokens
+}
+
+impl FromStr for SearchQuery {
+    type Err = TwtScrapeError;
+
+    fn from_str(s: &str) -> SResult<Self> {
+        let mut query = SearchQuery::new();
+        let count = |value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|why| SearchQueryError(format!("Bad count `{value}`: {why}")))
+        };
+
+        // anything inside an (a OR b) group is kept as typed
+        let mut depth = 0usize;
+        for token in query_tokens(s) {
+            let grouped = depth > 0 || token.starts_with('(');
+            depth += token.matches('(').count();
+            depth = depth.saturating_sub(token.matches(')').count());
+            if grouped {
+                query.terms.push(token.to_string());
+                continue;
+            }
+
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(body) => (true, body),
+                None => (false, token),
+            };
+            let (operator, value) = match body.split_once(':') {
+                Some((operator, value)) if !value.is_empty() => (operator, value),
+                _ => {
+                    query.terms.push(token.to_string());
+                    continue;
+                }
+            };
+
+            match (negated, operator) {
+                (false, "filter") => query.filters.push(SearchFilter::from_name(value)),
+                (true, "filter") => query.excluded_filters.push(SearchFilter::from_name(value)),
+                (false, "from") => query.from = Some(value.trim_start_matches('@').to_string()),
+                (false, "to") => query.to = Some(value.trim_start_matches('@').to_string()),
+                (false, "since") => query.since = Some(parse_search_date(value)?),
+                (false, "until") => query.until = Some(parse_search_date(value)?),
+                (false, "min_faves") => query.min_faves = Some(count(value)?),
+                (false, "min_retweets") => query.min_retweets = Some(count(value)?),
+                (false, "min_replies") => query.min_replies = Some(count(value)?),
+                (false, "lang") => query.lang = Some(value.to_string()),
+                (false, "conversation_id") => {
+                    query.conversation_id = Some(value.parse::<u64>().map_err(|why| {
+                        SearchQueryError(format!("Bad conversation id `{value}`: {why}"))
+                    })?)
+                }
+                (false, "geocode") => query.geocode = Some(value.parse()?),
+                _ => query.terms.push(token.to_string()),
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+#[test]
+fn build_and_parse_search_query() {
+    let query = SearchQuery::new()
+        .with_text("\"rust lang\"")
+        .with_from("@rustlang")
+        .with_since(Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap())
+        .with_until(Utc.with_ymd_and_hms(2023, 1, 2, 12, 0, 0).unwrap())
+        .with_min_faves(10)
+        .with_filter(SearchFilter::Media)
+        .without_filter(SearchFilter::Replies)
+        .with_lang("en")
+        .with_conversation_id(1234)
+        .with_geocode(37.78, -122.41, Distance::Kilometers(10.0));
+
+    let rendered = query.to_string();
+    assert_eq!(
+        rendered,
+        "\"rust lang\" from:rustlang conversation_id:1234 lang:en since:2023-01-01 \
+         until:2023-01-02_12:00:00_UTC min_faves:10 filter:media -filter:replies \
+         geocode:37.78,-122.41,10km"
+    );
+    assert_eq!(rendered.parse::<SearchQuery>().unwrap(), query);
+
+    // operators that aren't modelled stay as typed
+    let parsed: SearchQuery = "(from:a OR from:b) url:github.com -cats min_faves:5"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        parsed.terms,
+        vec!["(from:a", "OR", "from:b)", "url:github.com", "-cats"]
+    );
+    assert_eq!(parsed.min_faves, Some(5));
+
+    assert!("since:yesterday".parse::<SearchQuery>().is_err());
+}
+
+#[test]
+fn slice_search_into_date_windows() {
+    let query: SearchQuery = "rust since:2023-01-01 until:2023-01-03_12:00:00_UTC"
+        .parse()
+        .unwrap();
+
+    let days = query
+        .slices(DateSlice::Day)
+        .unwrap()
+        .iter()
+        .map(|window| window.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        days,
+        vec![
+            "rust since:2023-01-02_12:00:00_UTC until:2023-01-03_12:00:00_UTC",
+            "rust since:2023-01-01_12:00:00_UTC until:2023-01-02_12:00:00_UTC",
+            "rust since:2023-01-01 until:2023-01-01_12:00:00_UTC",
+        ]
+    );
+    assert_eq!(query.slices(DateSlice::Hour).unwrap().len(), 60);
+
+    let open_ended = SearchQuery::new().with_text("rust");
+    assert!(open_ended.slices(DateSlice::Day).is_err());
 }
 
 #[cfg(feature = "scrape")]
 impl Search {
-    #[tracing::instrument]
-    pub async fn make_query(scraper: &Scraper, query: impl AsRef<str>) -> SResult<Self> {
-        let first_request = scraper
-            .api_req::<SearchRequest>(
-                scraper.make_get_req(twitter_request_url_search(query.as_ref(), None)),
-            )
-            .await?;
+    pub fn stream_pages(
+        scraper: &Scraper,
+        query: impl Display,
+    ) -> impl Stream<Item = SResult<Page<SearchHit>>> + '_ {
+        Self::stream_pages_from(
+            scraper,
+            query,
+            SearchOptions::default(),
+            Checkpoint::default(),
+        )
+    }
 
-        let mut tweets = Vec::with_capacity(20);
+    pub fn stream_pages_from(
+        scraper: &Scraper,
+        query: impl Display,
+        options: SearchOptions,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<SearchHit>>> + '_ {
+        let (max_pages, max_results) = (options.max_pages, options.max_results);
+        let resume = start.clone();
+        let pages = match options.slice {
+            None => Self::query_pages(scraper, query.to_string(), options, start).left_stream(),
+            Some(slice) => {
+                Self::sliced_pages(scraper, query.to_string(), slice, options, start).right_stream()
+            }
+        };
+        limit(pages, resume, max_pages, max_results)
+    }
 
-        let mut next_cursor: Option<String> = None;
+    fn query_pages(
+        scraper: &Scraper,
+        query: String,
+        options: SearchOptions,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<SearchHit>>> + '_ {
+        paginate(start, move |cursor| {
+            let query = query.clone();
+            let options = options.clone();
+            async move { SearchRequest::fetch_page(scraper, &query, &options, cursor).await }
+        })
+    }
 
-        for inst in first_request.timeline.instructions {
-            if let Instruction::AddEntry(add) = inst {
-                for entry in add.entries {
-                    match entry.content {
-                        Entry::Item(item) => {
-                            if item.content.tweet.id.is_empty() || item.content.tweet.id == "0" {
-                                return Err(TwitterBadRestId(
-                                    "Search Tweet RestID",
-                                    item.content.tweet.id,
-                                ));
-                            }
+    // Each window is paged through on its own. When one runs dry its last checkpoint moves
+    // on to the start of the next window, so a saved checkpoint resumes mid slicing too.
+    fn sliced_pages(
+        scraper: &Scraper,
+        query: String,
+        slice: DateSlice,
+        options: SearchOptions,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<SearchHit>>> + '_ {
+        let windows = match query
+            .parse::<SearchQuery>()
+            .and_then(|query| query.slices(slice))
+        {
+            Ok(windows) => windows,
+            Err(why) => return stream::once(future::ready(Err(why))).left_stream(),
+        };
+        let ends = windows
+            .iter()
+            .map(|window| window.until)
+            .collect::<Vec<_>>();
+        let resume = start.window;
 
-                            tweets.push(item.content.tweet.id.parse::<u64>().map_err(|why| {
-                                TwitterBadRestId("Search Tweet RestID", why.to_string())
-                            })?);
-                        }
-                        Entry::Cursor(op) => {
-                            if entry.entry_id.starts_with("sq-cursor-bottom") {
-                                next_cursor = Some(op.cursor.value)
-                            }
-                        }
+        stream::iter(windows.into_iter().enumerate())
+            .skip_while(move |(_, window)| {
+                future::ready(matches!(
+                    (resume, window.until),
+                    (Some(end), Some(until)) if until > end
+                ))
+            })
+            .flat_map(move |(at, window)| {
+                let checkpoint = if resume.is_some() && resume == window.until {
+                    start.clone()
+                } else {
+                    Checkpoint {
+                        window: window.until,
+                        ..Checkpoint::default()
                     }
+                };
+                let next_window = ends.get(at + 1).copied().flatten();
+
+                Self::query_pages(scraper, window.to_string(), options.clone(), checkpoint).map_ok(
+                    move |mut page| {
+                        if page.checkpoint.finished && next_window.is_some() {
+                            page.checkpoint = Checkpoint {
+                                pages: page.checkpoint.pages,
+                                window: next_window,
+                                ..Checkpoint::default()
+                            };
+                        }
+                        page
+                    },
+                )
+            })
+            // a failed window ends the whole search, like any other page stream
+            .scan(false, |failed, page| {
+                if *failed {
+                    return future::ready(None);
+                }
+                *failed = page.is_err();
+                future::ready(Some(page))
+            })
+            .right_stream()
+    }
+
+    pub fn stream(
+        scraper: &Scraper,
+        query: impl Display,
+    ) -> impl Stream<Item = SResult<SearchHit>> + '_ {
+        items(Self::stream_pages(scraper, query))
+    }
+
+    #[tracing::instrument]
+    pub async fn make_query(scraper: &Scraper, query: impl Display + fmt::Debug) -> SResult<Self> {
+        Self::make_query_with(scraper, query, SearchOptions::default()).await
+    }
+
+    #[tracing::instrument]
+    pub async fn make_query_with(
+        scraper: &Scraper,
+        query: impl Display + fmt::Debug,
+        options: SearchOptions,
+    ) -> SResult<Self> {
+        Self::make_query_partial(scraper, query, options, Checkpoint::default())
+            .await
+            .into_result()
+    }
+
+    #[tracing::instrument]
+    pub async fn make_query_partial(
+        scraper: &Scraper,
+        query: impl Display + fmt::Debug,
+        options: SearchOptions,
+        start: Checkpoint,
+    ) -> PartialResult<Self> {
+        let pages = Self::stream_pages_from(scraper, query, options, start.clone());
+        collect_partial(pages, start).await.map(Self::from_hits)
+    }
+
+    fn from_hits(hits: Vec<SearchHit>) -> Self {
+        let mut search = Search {
+            tweets: Vec::with_capacity(hits.len()),
+            hydrated: vec![],
+            users: vec![],
+        };
+        let mut authors = HashSet::new();
+
+        for hit in hits {
+            let user = match hit {
+                SearchHit::Tweet { id, tweet, author } => {
+                    search.tweets.push(id);
+                    search.hydrated.extend(tweet);
+                    author
+                }
+                SearchHit::User { user, .. } => user,
+            };
+            if let Some(user) = user {
+                if authors.insert(user.id) {
+                    search.users.push(user);
                 }
             }
         }
 
-        if let Some(next) = next_cursor {
-            let mut cursor_counter = next;
-            loop {
-                let mut request = scraper
-                    .api_req::<SearchRequest>(scraper.make_get_req(twitter_request_url_search(
-                        query.as_ref(),
-                        Some(&cursor_counter),
-                    )))
-                    .await?;
-
-                for inst in request.timeline.instructions {
-                    match inst {
-                        Instruction::AddEntry(add) => {
-                            for entry in add.entries {
-                                if let Entry::Item(item) = entry {
-                                    if item.content.tweet.id.is_empty()
-                                        || item.content.tweet.id == "0"
-                                    {
-                                        return Err(TwitterBadRestId(
-                                            "Search Tweet RestID",
-                                            item.content.tweet.id,
-                                        ));
-                                    }
-
-                                    tweets.push(item.content.tweet.id.parse::<u64>().map_err(
-                                        |why| {
-                                            TwitterBadRestId("Search Tweet RestID", why.to_string())
-                                        },
-                                    )?);
+        search
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SearchRequest {
+    pub timeline: Timeline,
+    #[serde(rename = "globalObjects", default)]
+    pub global_objects: GlobalObjects,
+}
+
+#[cfg(feature = "scrape")]
+impl SearchRequest {
+    #[tracing::instrument]
+    pub(crate) async fn fetch_page(
+        scraper: &Scraper,
+        query: &str,
+        options: &SearchOptions,
+        cursor: Option<String>,
+    ) -> SResult<Page<SearchHit>> {
+        let request = scraper
+            .api_req::<SearchRequest>(scraper.make_get_req(twitter_request_url_search(
+                query,
+                options.mode,
+                cursor,
+            )))
+            .await?;
+
+        let mut hits = Vec::with_capacity(20);
+        let mut errors = vec![];
+        let mut next_cursor = None;
+
+        // the first page adds the bottom cursor, later pages replace it
+        for inst in request.timeline.instructions {
+            match inst {
+                Instruction::AddEntry(add) => {
+                    for entry in add.entries {
+                        match entry {
+                            Entry::Item(item) => match (item.id(), item.content()) {
+                                (Ok(id), Some(ItemContent { user: Some(_), .. })) => hits
+                                    .push(hydrate_user(&request.global_objects, id, &mut errors)),
+                                (Ok(id), _) if options.hydrate => {
+                                    hits.push(hydrate(&request.global_objects, id, &mut errors))
                                 }
-                            }
-                        }
-                        Instruction::ReplaceEntry(replace) => {
-                            if replace.entry_id_to_replace.starts_with("sq-cursor-bottom") {
-                                if let Entry::Cursor(op) = replace.entry {
-                                    match op.cursor.value.strip_prefix("") {
-                                        Some(new) => {
-                                            cursor_counter = new.to_string();
-                                        }
-                                        None => break,
-                                    }
+                                (Ok(id), _) => hits.push(SearchHit::Tweet {
+                                    id,
+                                    tweet: None,
+                                    author: None,
+                                }),
+                                (Err((raw, why)), _) => {
+                                    warn!(error = %why, "Bad id in search. Skipping...");
+                                    errors.push(ItemError::new(raw, &why));
+                                }
+                            },
+                            Entry::Cursor(entry_id, op) => {
+                                if entry_id.starts_with("sq-cursor-bottom") {
+                                    next_cursor = Some(op.cursor.value)
                                 }
-                                break;
                             }
                         }
                     }
                 }
+                Instruction::ReplaceEntry(replace) => {
+                    if replace.entry_id_to_replace.starts_with("sq-cursor-bottom") {
+                        if let Entry::Cursor(_, op) = replace.entry {
+                            next_cursor = Some(op.cursor.value)
+                        }
+                    }
+                }
             }
         }
 
-        tweets.shrink_to_fit();
+        Ok(Page::new(hits, next_cursor).with_errors(errors))
+    }
+}
+
+// A tweet or author that can't be converted still leaves the hit with its id.
+#[cfg(feature = "scrape")]
+fn hydrate(objects: &GlobalObjects, id: u64, errors: &mut Vec<ItemError>) -> SearchHit {
+    let (mut tweet, mut author) = (None, None);
+
+    let legacy = match objects.tweets.get(&id.to_string()) {
+        Some(legacy) => legacy,
+        None => {
+            let why = BadJSONSchema("Search globalObjects", format!("No Tweet {id}"));
+            errors.push(ItemError::new(id, &why));
+            return SearchHit::Tweet { id, tweet, author };
+        }
+    };
+
+    match Tweet::try_from(legacy) {
+        Ok(parsed) => tweet = Some(parsed),
+        Err(why) => {
+            warn!(error = %why, id, "Failed to hydrate search tweet.");
+            errors.push(ItemError::new(id, &why));
+        }
+    }
 
-        Ok(Self { tweets })
+    match objects.users.get(&legacy.user_id_str).map(User::try_from) {
+        Some(Ok(user)) => author = Some(user),
+        Some(Err(why)) => {
+            warn!(error = %why, id, "Failed to hydrate search tweet author.");
+            errors.push(ItemError::new(&legacy.user_id_str, &why));
+        }
+        None => {}
     }
+
+    SearchHit::Tweet { id, tweet, author }
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub(crate) struct SearchRequest {
-    pub timeline: Timeline,
+#[cfg(feature = "scrape")]
+fn hydrate_user(objects: &GlobalObjects, id: u64, errors: &mut Vec<ItemError>) -> SearchHit {
+    let user = match objects.users.get(&id.to_string()).map(User::try_from) {
+        Some(Ok(user)) => Some(user),
+        Some(Err(why)) => {
+            warn!(error = %why, id, "Failed to hydrate search user.");
+            errors.push(ItemError::new(id, &why));
+            None
+        }
+        None => {
+            let why = BadJSONSchema("Search globalObjects", format!("No User {id}"));
+            errors.push(ItemError::new(id, &why));
+            None
+        }
+    };
+
+    SearchHit::User { id, user }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -157,7 +938,7 @@ pub(crate) struct Timeline {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) enum Instruction {
-    #[serde(rename(deserialize = "addEntry"))]
+    #[serde(rename(deserialize = "addEntry"), alias = "addEntries")]
     AddEntry(AddEntry),
     #[serde(rename(deserialize = "replaceEntry"))]
     ReplaceEntry(ReplaceEntry),
@@ -178,7 +959,8 @@ pub(crate) struct AddEntry {
 #[derive(Clone, Debug, PartialEq, Serialize)]
 pub(crate) enum Entry {
     Item(Item),
-    Cursor(Operation),
+    // the cursor's entry id tells top from bottom
+    Cursor(String, Operation),
 }
 
 impl<'de> Deserialize<'de> for Entry {
@@ -240,26 +1022,26 @@ impl<'de> Deserialize<'de> for Entry {
                 V: MapAccess<'de>,
             {
                 let mut entry_id: Option<String> = None;
-                let mut sort_index: Option<String> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::EntryId => {
                             entry_id = Some(map.next_value()?);
                         }
                         Field::SortIndex => {
-                            sort_index = Some(map.next_value()?);
+                            map.next_value::<de::IgnoredAny>()?;
                         }
                         Field::Content => {
-                            if let Some(entry) = &entry_id {
-                                if entry.starts_with("sq-I") {
+                            if let Some(entry) = entry_id {
+                                return if entry.starts_with("sq-I") {
                                     Ok(Entry::Item(map.next_value()?))
                                 } else if entry.starts_with("sq-cursor") {
-                                    Ok(Entry::Cursor(map.next_value()?))
+                                    let content = map.next_value::<CursorContent>()?;
+                                    Ok(Entry::Cursor(entry, content.operation))
                                 } else {
-                                    Err(de::Error::unknown_variant(entry, &["sq-I", "sq-cursor"]))
-                                }
+                                    Err(de::Error::unknown_variant(&entry, &["sq-I", "sq-cursor"]))
+                                };
                             }
-                            Err(de::Error::unknown_variant("None", &["sq-I", "sq-cursor"]))
+                            return Err(de::Error::unknown_variant("None", &["sq-I", "sq-cursor"]));
                         }
                     }
                 }
@@ -267,11 +1049,17 @@ impl<'de> Deserialize<'de> for Entry {
             }
         }
 
-        const VARIANTS: &[&str] = &["Item", "Cursor"];
-        deserializer.deserialize_enum("Entry", VARIANTS, EntryVisitor)
+        // a plain object, the entry id picks the variant
+        deserializer.deserialize_map(EntryVisitor)
     }
 }
 
+// a cursor entry's content, `{"operation": {"cursor": {..}}}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CursorContent {
+    pub operation: Operation,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Operation {
     pub cursor: Cursor,
@@ -284,15 +1072,239 @@ pub(crate) struct Cursor {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Item {
+    // `{"item": {"content": {..}}}`, older responses skip the `item`
+    pub item: Option<ItemWrapper>,
+    pub content: Option<ItemContent>,
+}
+
+impl Item {
+    pub(crate) fn content(&self) -> Option<&ItemContent> {
+        self.item
+            .as_ref()
+            .map(|item| &item.content)
+            .or(self.content.as_ref())
+    }
+
+    // the raw id comes back with the error for reporting
+    pub(crate) fn id(&self) -> Result<u64, (String, TwtScrapeError)> {
+        let (kind, id) = match self.content() {
+            Some(ItemContent {
+                user: Some(user), ..
+            }) => ("Search User RestID", &user.id),
+            Some(ItemContent {
+                tweet: Some(tweet), ..
+            }) => ("Search Tweet RestID", &tweet.id),
+            _ => {
+                return Err((
+                    String::new(),
+                    BadJSONSchema("Search Item", "No Tweet or User".to_string()),
+                ))
+            }
+        };
+        if id.is_empty() || id == "0" {
+            return Err((id.clone(), TwitterBadRestId(kind, id.clone())));
+        }
+        id.parse::<u64>()
+            .map_err(|why| (id.clone(), TwitterBadRestId(kind, why.to_string())))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ItemWrapper {
     pub content: ItemContent,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct ItemContent {
-    pub tweet: SearchTweet,
+    pub tweet: Option<SearchTweet>,
+    pub user: Option<SearchUser>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct SearchTweet {
     pub id: String,
 }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SearchUser {
+    pub id: String,
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn hydrate_search_from_global_objects() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(FixtureTransport::new().with_json(
+            "https://twitter.com/i/api/2/search/adaptive.json",
+            include_str!("../fixtures/search/adaptive.json"),
+        ));
+        let scraper = fixture_scraper(&transport).await;
+
+        // the default mode hydrates without being asked to
+        let page =
+            SearchRequest::fetch_page(&scraper, "#rustlang", &SearchOptions::default(), None)
+                .await
+                .unwrap();
+        assert_eq!(page.next_cursor.as_deref(), Some("scroll:2"));
+
+        let ids = page
+            .items
+            .iter()
+            .map(|hit| match hit {
+                SearchHit::Tweet { id, .. } | SearchHit::User { id, .. } => *id,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                1700000000000000002,
+                1700000000000000005,
+                1700000000000000006,
+                1700000000000000007
+            ]
+        );
+
+        // a bad date and a tweet missing from globalObjects still leave their hits
+        let failed = page
+            .errors
+            .iter()
+            .map(|error| error.item.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec!["1700000000000000006", "1700000000000000007"]);
+
+        let search = Search::from_hits(page.items);
+        assert_eq!(search.tweets.len(), 4);
+        assert_eq!(
+            search
+                .hydrated
+                .iter()
+                .map(|tweet| tweet.id)
+                .collect::<Vec<_>>(),
+            vec![1700000000000000002, 1700000000000000005]
+        );
+        // both hydrated tweets share an author
+        assert_eq!(search.users.len(), 1);
+        assert_eq!(search.users[0].name.handle, "crabbyrust");
+
+        // naming the default mode changes nothing
+        let options = SearchOptions::new().with_mode(SearchMode::Latest);
+        let latest = SearchRequest::fetch_page(&scraper, "#rustlang", &options, None)
+            .await
+            .unwrap();
+        assert_eq!(Search::from_hits(latest.items).hydrated.len(), 2);
+
+        // without hydration only the ids come back
+        let options = SearchOptions::new().without_hydration();
+        let page = SearchRequest::fetch_page(&scraper, "#rustlang", &options, None)
+            .await
+            .unwrap();
+        assert!(page.errors.is_empty());
+        assert!(page.items.iter().all(|hit| matches!(
+            hit,
+            SearchHit::Tweet {
+                tweet: None,
+                author: None,
+                ..
+            }
+        )));
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn search_url_per_mode() {
+    let params = |mode| {
+        let url = Url::parse(&twitter_request_url_search("rust", mode, Some("scroll:2"))).unwrap();
+        url.query_pairs()
+            .filter(|(key, _)| matches!(key.as_ref(), "tweet_search_mode" | "result_filter"))
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(params(SearchMode::Top).is_empty());
+    assert_eq!(params(SearchMode::Latest), vec!["tweet_search_mode=live"]);
+    assert_eq!(params(SearchMode::People), vec!["result_filter=user"]);
+    assert_eq!(params(SearchMode::Photos), vec!["result_filter=image"]);
+    assert_eq!(params(SearchMode::Videos), vec!["result_filter=video"]);
+
+    let url = Url::parse(&twitter_request_url_search(
+        "rust",
+        SearchMode::People,
+        Some("scroll:2"),
+    ))
+    .unwrap();
+    let pairs = url.query_pairs().collect::<Vec<_>>();
+    assert!(pairs.contains(&("q".into(), "rust".into())));
+    assert!(pairs.contains(&("cursor".into(), "scroll:2".into())));
+
+    // every mode hydrates unless told not to, the default one included
+    let default = SearchOptions::default();
+    assert_eq!(default.mode, SearchMode::Latest);
+    assert!(default.hydrate);
+    assert_eq!(SearchOptions::new().with_mode(SearchMode::Latest), default);
+    assert!(SearchOptions::new().with_mode(SearchMode::Top).hydrate);
+    assert!(
+        !SearchOptions::new()
+            .without_hydration()
+            .with_mode(SearchMode::Photos)
+            .hydrate
+    );
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn search_people_and_modes() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    "https://twitter.com/i/api/2/search/adaptive.json?count=20&query_source=typed_query&pc=1&spelling_corrections=1&result_filter=user",
+                    include_str!("../fixtures/search/adaptive_people.json"),
+                )
+                .with_json(
+                    "https://twitter.com/i/api/2/search/adaptive.json?count=20&query_source=typed_query&pc=1&spelling_corrections=1&result_filter=image",
+                    include_str!("../fixtures/search/adaptive.json"),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        let options = SearchOptions::new()
+            .with_mode(SearchMode::People)
+            .with_max_pages(1);
+        let page = SearchRequest::fetch_page(&scraper, "crab", &options, None)
+            .await
+            .unwrap();
+        // the replaced bottom cursor wins
+        assert_eq!(page.next_cursor.as_deref(), Some("scroll:people-2"));
+        assert!(page.errors.is_empty());
+
+        let search = Search::make_query_with(&scraper, "crab", options)
+            .await
+            .unwrap();
+        assert!(search.tweets.is_empty());
+        let handles = search
+            .users
+            .iter()
+            .map(|user| (user.id, user.name.handle.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(handles, vec![(3308337, "crabbyrust"), (3308338, "ferris")]);
+        assert_eq!(search.users[1].profile_stats.followers, 9000);
+
+        // other modes hydrate the same way
+        let options = SearchOptions::new()
+            .with_mode(SearchMode::Photos)
+            .with_max_pages(1);
+        let page = SearchRequest::fetch_page(&scraper, "crab", &options, None)
+            .await
+            .unwrap();
+        let search = Search::from_hits(page.items);
+        assert_eq!(search.hydrated.len(), 2);
+        assert_eq!(search.users[0].name.handle, "crabbyrust");
+    });
+}
@@ -0,0 +1,356 @@
+use crate::error::SResult;
+use crate::error::TwtScrapeError::SnapshotMismatch;
+#[cfg(feature = "scrape")]
+use crate::scrape::Scraper;
+use crate::user::{Unavailability, User};
+#[cfg(feature = "scrape")]
+use ahash::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+#[cfg(feature = "scrape")]
+use futures::{future, stream, Stream, StreamExt};
+use rkyv::Archive;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "scrape")]
+use std::time::Duration;
+
+// A user as they were at `taken`.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
+pub struct UserSnapshot {
+    pub taken: DateTime<Utc>,
+    pub user: User,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum UserChange {
+    Handle(Change<String>),
+    DisplayName(Change<String>),
+    Bio(Change<String>),
+    Avatar(Change<String>),
+    Banner(Change<String>),
+    Location(Change<Option<String>>),
+    Website(Change<Option<String>>),
+    PinnedTweet(Change<Option<u64>>),
+    Tweets(Change<u32>),
+    Following(Change<u32>),
+    Followers(Change<u32>),
+    Likes(Change<u32>),
+    Listed(Change<u32>),
+    Verified(Change<bool>),
+    BlueVerified(Change<bool>),
+    Protected(Change<bool>),
+    Sensitive(Change<bool>),
+}
+
+macro_rules! push_change {
+    ($changes:ident, $variant:ident, $old:expr, $new:expr) => {
+        if $old != $new {
+            $changes.push(UserChange::$variant(Change {
+                old: $old.clone(),
+                new: $new.clone(),
+            }));
+        }
+    };
+}
+
+impl UserSnapshot {
+    pub fn new(user: User) -> Self {
+        Self::at(user, Utc::now())
+    }
+
+    pub fn at(user: User, taken: DateTime<Utc>) -> Self {
+        UserSnapshot { taken, user }
+    }
+
+    // What changed going from this snapshot to `newer`. Both have to be the same account,
+    // a renamed handle shows up as a `Handle` change.
+    pub fn diff(&self, newer: &UserSnapshot) -> SResult<Vec<UserChange>> {
+        let (old, new) = (&self.user, &newer.user);
+        if old.id != new.id {
+            return Err(SnapshotMismatch(old.id, new.id));
+        }
+
+        let mut changes = vec![];
+        push_change!(changes, Handle, old.name.handle, new.name.handle);
+        push_change!(changes, DisplayName, old.name.display, new.name.display);
+        push_change!(changes, Bio, old.bio, new.bio);
+        push_change!(changes, Avatar, old.avatar.url, new.avatar.url);
+        push_change!(changes, Banner, old.avatar.banner, new.avatar.banner);
+        push_change!(
+            changes,
+            Location,
+            old.additional_info.location,
+            new.additional_info.location
+        );
+        push_change!(
+            changes,
+            Website,
+            old.additional_info.website,
+            new.additional_info.website
+        );
+        push_change!(
+            changes,
+            PinnedTweet,
+            old.pinned_tweet_id,
+            new.pinned_tweet_id
+        );
+
+        let (old_stats, new_stats) = (&old.profile_stats, &new.profile_stats);
+        push_change!(changes, Tweets, old_stats.tweets, new_stats.tweets);
+        push_change!(changes, Following, old_stats.following, new_stats.following);
+        push_change!(changes, Followers, old_stats.followers, new_stats.followers);
+        push_change!(changes, Likes, old_stats.likes, new_stats.likes);
+        push_change!(changes, Listed, old_stats.listed, new_stats.listed);
+        push_change!(changes, Verified, old_stats.verified, new_stats.verified);
+        push_change!(
+            changes,
+            BlueVerified,
+            old_stats.blue_verified,
+            new_stats.blue_verified
+        );
+        push_change!(changes, Protected, old.is_protected, new.is_protected);
+        push_change!(changes, Sensitive, old.is_sensitive, new.is_sensitive);
+
+        Ok(changes)
+    }
+}
+
+// Every snapshot of one account, oldest first.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserHistory {
+    pub snapshots: Vec<UserSnapshot>,
+}
+
+impl UserHistory {
+    pub fn latest(&self) -> Option<&UserSnapshot> {
+        self.snapshots.last()
+    }
+
+    // adds `snapshot` and returns what changed since the latest one
+    pub fn record(&mut self, snapshot: UserSnapshot) -> SResult<Vec<UserChange>> {
+        let changes = match self.latest() {
+            Some(latest) => latest.diff(&snapshot)?,
+            None => vec![],
+        };
+        self.snapshots.push(snapshot);
+        Ok(changes)
+    }
+
+    // every change in order, with when it was first seen
+    pub fn changes(&self) -> SResult<Vec<(DateTime<Utc>, UserChange)>> {
+        let mut all = vec![];
+        for pair in self.snapshots.windows(2) {
+            all.extend(
+                pair[0]
+                    .diff(&pair[1])?
+                    .into_iter()
+                    .map(|change| (pair[1].taken, change)),
+            );
+        }
+        Ok(all)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchEvent {
+    // the first snapshot of a user
+    Found(UserSnapshot),
+    Changed {
+        snapshot: UserSnapshot,
+        changes: Vec<UserChange>,
+    },
+    // the user stopped coming back, e.g. suspended. Sent once until they are back.
+    Unavailable {
+        id: u64,
+        reason: Unavailability,
+    },
+}
+
+// Refetches a list of users every `interval` and reports what changed since the last time.
+// Uses UsersByRestIds, so a round costs one request per 100 users.
+#[cfg(feature = "scrape")]
+#[derive(Clone, Debug)]
+pub struct UserWatcher {
+    ids: Vec<u64>,
+    interval: Duration,
+    latest: HashMap<u64, UserSnapshot>,
+    unavailable: HashSet<u64>,
+}
+
+#[cfg(feature = "scrape")]
+impl UserWatcher {
+    pub fn new(ids: impl IntoIterator<Item = u64>, interval: Duration) -> Self {
+        UserWatcher {
+            ids: ids.into_iter().collect(),
+            interval,
+            latest: HashMap::default(),
+            unavailable: HashSet::default(),
+        }
+    }
+
+    // picks up from stored snapshots, so the first round only reports changes
+    pub fn with_snapshots(mut self, snapshots: impl IntoIterator<Item = UserSnapshot>) -> Self {
+        for snapshot in snapshots {
+            self.latest.insert(snapshot.user.id, snapshot);
+        }
+        self
+    }
+
+    pub fn latest(&self, id: u64) -> Option<&UserSnapshot> {
+        self.latest.get(&id)
+    }
+
+    // one round, without waiting
+    #[tracing::instrument(skip(self))]
+    pub async fn poll(&mut self, scraper: &Scraper) -> SResult<Vec<WatchEvent>> {
+        let batch = User::by_ids(scraper, self.ids.iter().copied()).await?;
+        let mut events = vec![];
+
+        for user in batch.users {
+            self.unavailable.remove(&user.id);
+            let snapshot = UserSnapshot::new(user);
+            match self.latest.get(&snapshot.user.id) {
+                Some(latest) => {
+                    let changes = latest.diff(&snapshot)?;
+                    if !changes.is_empty() {
+                        events.push(WatchEvent::Changed {
+                            snapshot: snapshot.clone(),
+                            changes,
+                        });
+                    }
+                }
+                None => events.push(WatchEvent::Found(snapshot.clone())),
+            }
+            self.latest.insert(snapshot.user.id, snapshot);
+        }
+
+        for (id, reason) in batch.unavailable {
+            if self.unavailable.insert(id) {
+                events.push(WatchEvent::Unavailable { id, reason });
+            }
+        }
+
+        Ok(events)
+    }
+
+    // Polls forever, the first round right away. A failed round is yielded as an error and
+    // the next one is tried as usual.
+    pub fn watch(self, scraper: &Scraper) -> impl Stream<Item = SResult<WatchEvent>> + '_ {
+        stream::unfold((self, true), move |(mut watcher, first)| async move {
+            if !first {
+                tokio::time::sleep(watcher.interval).await;
+            }
+            let events = watcher.poll(scraper).await;
+            Some((events, (watcher, false)))
+        })
+        .flat_map(|events| match events {
+            Ok(events) => stream::iter(events.into_iter().map(Ok)).left_stream(),
+            Err(why) => stream::once(future::ready(Err(why))).right_stream(),
+        })
+    }
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn watch_for_profile_changes() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let users = include_str!("../fixtures/user/users_by_rest_ids.json");
+        let url = "https://twitter.com/i/api/graphql/OJBgJQIrij6e3cjqQ3Zu1Q/UsersByRestIds";
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(url, users)
+                .with_json(url, users)
+                .with_json(
+                    url,
+                    users
+                        .replace("\"crabbyrust\"", "\"crabbier\"")
+                        .replace("\"followers_count\": 340", "\"followers_count\": 341"),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        let mut watcher = UserWatcher::new([3308337, 404], Duration::from_millis(1));
+        let events = watcher.poll(&scraper).await.unwrap();
+        assert!(matches!(&events[0], WatchEvent::Found(found) if found.user.id == 3308337));
+        assert_eq!(
+            events[1],
+            WatchEvent::Unavailable {
+                id: 404,
+                reason: Unavailability::not_found(),
+            }
+        );
+
+        // nothing changed and 404 was already reported
+        assert!(watcher.poll(&scraper).await.unwrap().is_empty());
+
+        let first = watcher.latest(3308337).unwrap().clone();
+        let events = watcher.poll(&scraper).await.unwrap();
+        let changes = match &events[..] {
+            [WatchEvent::Changed { changes, .. }] => changes.clone(),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(
+            changes,
+            vec![
+                UserChange::Handle(Change {
+                    old: "crabbyrust".to_string(),
+                    new: "crabbier".to_string(),
+                }),
+                UserChange::Followers(Change { old: 340, new: 341 }),
+            ]
+        );
+
+        let mut history = UserHistory::default();
+        history.record(first).unwrap();
+        let latest = watcher.latest(3308337).unwrap().clone();
+        assert_eq!(history.record(latest).unwrap(), changes);
+        assert_eq!(history.changes().unwrap().len(), 2);
+
+        let mut other = history.latest().unwrap().clone();
+        other.user.id = 1;
+        assert!(history.record(other).is_err());
+    });
+}
@@ -1,44 +1,86 @@
+#[cfg(feature = "scrape")]
+use crate::error::{
+    SResult, TwtScrapeError,
+    TwtScrapeError::{TwitterBadRestId, TwitterBadTimeParse},
+};
+#[cfg(feature = "scrape")]
+use crate::tweet;
+use crate::tweet::TweetUserMentions;
+use crate::user::UserEntities;
+#[cfg(feature = "scrape")]
+use crate::user::{self, JOINDATE_PARSE_STR};
+#[cfg(feature = "scrape")]
+use chrono::{DateTime, Utc};
 use rkyv::Archive;
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+// The v1.1 shaped `globalObjects` that `adaptive.json` endpoints like search send alongside
+// their timeline, every tweet and user the timeline mentions keyed by id.
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct GlobalTimeline {
-    #[serde(alias = "globalObjects")]
-    global_objects: GlobalObjects,
+    #[serde(alias = "globalObjects", default)]
+    pub global_objects: GlobalObjects,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct GlobalObjects {
-    tweets: HashMap<String, Tweet>,
+    #[serde(default)]
+    pub tweets: HashMap<String, Tweet>,
+    #[serde(default)]
+    pub users: HashMap<String, User>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Tweet {
+    pub id_str: String,
+    pub user_id_str: String,
     pub conversation_id_str: String,
     pub created_at: String,
-    pub favorite_count: i32,
+    #[serde(default)]
+    pub favorite_count: u32,
+    #[serde(default)]
+    pub retweet_count: u32,
+    #[serde(default)]
+    pub reply_count: u32,
+    #[serde(default)]
+    pub quote_count: u32,
     pub full_text: String,
+    #[serde(default)]
+    pub display_text_range: Vec<u16>,
+    #[serde(default)]
     pub entities: Entities,
+    #[serde(default)]
     pub extended_entities: ExtendedEntities,
-    pub in_reply_to_status_id_str: String,
-    pub place: Place,
+    pub in_reply_to_status_id_str: Option<String>,
+    #[serde(default)]
+    pub is_quote_status: bool,
+    pub quoted_status_id_str: Option<String>,
+    #[serde(default)]
+    pub source: String,
+    pub place: Option<Place>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Entities {
+    #[serde(default)]
     pub hashtags: Vec<Hashtag>,
+    #[serde(default)]
     pub media: Vec<Media>,
+    #[serde(default)]
     pub urls: Vec<Url>,
+    #[serde(default)]
+    pub user_mentions: Vec<TweetUserMentions>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Hashtag {
     pub text: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Media {
     pub media_url_https: String,
     #[serde(alias = "type")]
@@ -46,46 +88,285 @@ pub struct Media {
     pub url: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Url {
     pub expanded_url: String,
     pub url: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ExtendedEntities {
-    pub media: ExtendedMedia,
+    #[serde(default)]
+    pub media: Vec<ExtendedMedia>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ExtendedMedia {
     pub id_str: String,
+    #[serde(default)]
+    pub media_key: String,
     pub media_url_https: String,
-    pub ext_sensitive_media_warning: ExtSensitiveMediaWarning,
+    pub ext_sensitive_media_warning: Option<ExtSensitiveMediaWarning>,
     #[serde(alias = "type")]
     pub media_type: String,
     pub url: String,
-    pub video_info: VideoInfo,
+    #[serde(default)]
+    pub expanded_url: String,
+    pub ext_alt_text: Option<String>,
+    pub video_info: Option<VideoInfo>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ExtSensitiveMediaWarning {
+    #[serde(default)]
     adult_content: bool,
+    #[serde(default)]
     graphic_violence: bool,
+    #[serde(default)]
     other: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VideoInfo {
     variants: Vec<VideoVariant>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VideoVariant {
+    #[serde(default)]
     bitrate: i32,
     url: String,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id_str: String,
+    pub name: String,
+    pub screen_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub entities: UserEntities,
+    #[serde(default)]
+    pub followers_count: u32,
+    #[serde(default)]
+    pub normal_followers_count: u32,
+    #[serde(default)]
+    pub listed_count: u32,
+    #[serde(default)]
+    pub friends_count: u32,
+    #[serde(default)]
+    pub statuses_count: u32,
+    #[serde(default)]
+    pub favourites_count: u32,
+    #[serde(default)]
+    pub media_count: u32,
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub possibly_sensitive: bool,
+    #[serde(default)]
+    pub profile_image_url_https: String,
+    #[serde(default)]
+    pub profile_banner_url: String,
+    #[serde(default)]
+    pub pinned_tweet_ids_str: Vec<String>,
+    #[serde(default)]
+    pub ext_has_nft_avatar: bool,
+    #[serde(default)]
+    pub ext_is_blue_verified: bool,
+    #[serde(default)]
+    pub default_profile: bool,
+    #[serde(default)]
+    pub default_profile_image: bool,
+    #[serde(default)]
+    pub has_custom_timelines: bool,
+    #[serde(default)]
+    pub is_translator: bool,
+    #[serde(default)]
+    pub profile_interstitial_type: String,
+    #[serde(default)]
+    pub withheld_in_countries: Vec<String>,
+}
+
+#[cfg(feature = "scrape")]
+fn parse_id(kind: &'static str, id: &str) -> SResult<u64> {
+    if id.is_empty() || id == "0" {
+        return Err(TwitterBadRestId(kind, id.to_string()));
+    }
+    id.parse::<u64>()
+        .map_err(|why| TwitterBadRestId(kind, why.to_string()))
+}
+
+#[cfg(feature = "scrape")]
+fn parse_optional_id(kind: &'static str, id: &Option<String>) -> SResult<Option<u64>> {
+    match id.as_deref() {
+        None | Some("") | Some("0") => Ok(None),
+        Some(id) => parse_id(kind, id).map(Some),
+    }
+}
+
+#[cfg(feature = "scrape")]
+fn parse_date(date: &str, format: &str) -> SResult<DateTime<Utc>> {
+    DateTime::parse_from_str(date, format)
+        .map(DateTime::<Utc>::from)
+        .map_err(|why| TwitterBadTimeParse(why.to_string()))
+}
+
+#[cfg(feature = "scrape")]
+impl TryFrom<&Tweet> for tweet::Tweet {
+    type Error = TwtScrapeError;
+
+    fn try_from(legacy: &Tweet) -> SResult<Self> {
+        let media = legacy
+            .extended_entities
+            .media
+            .iter()
+            .map(|x| {
+                Ok(tweet::Media {
+                    id: parse_id("Media ID", &x.id_str)?,
+                    media_key: x.media_key.clone(),
+                    media_url_https: x.media_url_https.clone(),
+                    r#type: x.media_type.clone(),
+                    expanded_url: x.expanded_url.clone(),
+                    ext_alt_text: x.ext_alt_text.clone(),
+                    views: None,
+                })
+            })
+            .collect::<SResult<Vec<tweet::Media>>>()?;
+
+        let display_text_range = match legacy.display_text_range.as_slice() {
+            [start, end] => (*start, *end),
+            _ => (0, legacy.full_text.chars().count() as u16),
+        };
+
+        let quoting = if legacy.is_quote_status {
+            parse_optional_id("Quote Tweet ID", &legacy.quoted_status_id_str)?
+        } else {
+            None
+        };
+
+        Ok(tweet::Tweet {
+            id: parse_id("Tweet RestID", &legacy.id_str)?,
+            conversation_id: parse_id("Conversation RestID", &legacy.conversation_id_str)?,
+            posted_id: Some(parse_id("User RestID", &legacy.user_id_str)?),
+            tweet_type: tweet::TweetType::Tweet(Box::new(tweet::TweetData {
+                created: parse_date(&legacy.created_at, tweet::TWEET_CREATED_DATETIME)?,
+                edit_ids: vec![],
+                entry: tweet::Entries {
+                    media,
+                    mentions: legacy.entities.user_mentions.clone(),
+                    urls: legacy
+                        .entities
+                        .urls
+                        .iter()
+                        .map(|url| url.expanded_url.clone())
+                        .collect(),
+                    hashtags: legacy
+                        .entities
+                        .hashtags
+                        .iter()
+                        .map(|ht| ht.text.clone())
+                        .collect(),
+                },
+                card: None,
+                text: legacy.full_text.clone(),
+                source: tweet::source_name(&legacy.source),
+                display_text_range,
+                stats: tweet::TweetStats {
+                    quote_tweets: legacy.quote_count,
+                    retweets: legacy.retweet_count,
+                    likes: legacy.favorite_count,
+                    replies: legacy.reply_count,
+                },
+                reply_info: tweet::ReplyInfo {
+                    replying_to: parse_optional_id(
+                        "Reply Tweet ID",
+                        &legacy.in_reply_to_status_id_str,
+                    )?,
+                    quoting,
+                },
+                moderated: false,
+                conversation_control: tweet::ConversationControl::None,
+                vibe: None,
+                place: legacy.place.clone(),
+                collaborators: None,
+            })),
+        })
+    }
+}
+
+// The website comes straight from the profile's entities, no redirect to follow.
+#[cfg(feature = "scrape")]
+impl TryFrom<&User> for user::User {
+    type Error = TwtScrapeError;
+
+    fn try_from(legacy: &User) -> SResult<Self> {
+        let mut entities = legacy.entities.clone();
+        entities.scan_bio(&legacy.description);
+
+        Ok(user::User {
+            id: parse_id("User RestID", &legacy.id_str)?,
+            avatar: user::Avatar {
+                url: legacy.profile_image_url_https.clone(),
+                banner: legacy.profile_banner_url.clone(),
+                is_nft: legacy.ext_has_nft_avatar,
+            },
+            name: user::ProfileName {
+                display: legacy.name.clone(),
+                handle: legacy.screen_name.clone(),
+            },
+            profile_stats: user::ProfileStats {
+                tweets: legacy.statuses_count,
+                following: legacy.friends_count,
+                followers: legacy.followers_count,
+                likes: legacy.favourites_count,
+                media_tweets: legacy.media_count,
+                verified: legacy.verified,
+                blue_verified: legacy.ext_is_blue_verified,
+                listed: legacy.listed_count,
+                normal_followers: legacy.normal_followers_count,
+            },
+            additional_info: user::ProfileAdditionalInfo {
+                affiliation: None,
+                profession: None,
+                location: crate::as_option!(legacy.location.clone(), "", "0"),
+                website: legacy
+                    .entities
+                    .url
+                    .urls
+                    .first()
+                    .map(|url| url.expanded_url.clone()),
+                joined: parse_date(&legacy.created_at, JOINDATE_PARSE_STR)?,
+                birthday: None,
+                withheld_in: legacy.withheld_in_countries.clone(),
+            },
+            bio: legacy.description.clone(),
+            pinned_tweet_id: legacy
+                .pinned_tweet_ids_str
+                .first()
+                .and_then(|id| id.parse::<u64>().ok()),
+            is_sensitive: legacy.possibly_sensitive,
+            is_protected: legacy.protected,
+            flags: user::ProfileFlags {
+                default_profile: legacy.default_profile,
+                default_profile_image: legacy.default_profile_image,
+                has_custom_timelines: legacy.has_custom_timelines,
+                is_translator: legacy.is_translator,
+                is_profile_translatable: false,
+                super_follow_eligible: false,
+                interstitial: crate::as_option!(legacy.profile_interstitial_type.clone(), ""),
+            },
+            entities,
+        })
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -109,19 +390,69 @@ pub struct Place {
 }
 
 #[derive(
-    Clone,
-    Debug,
-    Hash,
-    PartialEq,
-    Eq,
-    Serialize,
-    Deserialize,
-    Archive,
-    rkyv::Serialize,
-    rkyv::Deserialize,
+    Clone, Debug, PartialEq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
 )]
 pub struct BoundingBox {
     #[serde(alias = "type")]
     pub box_type: String,
     pub coordinates: Vec<Vec<Vec<f64>>>,
 }
+
+// coordinates are never NaN, so comparing and hashing their bits is sound
+impl Eq for BoundingBox {}
+
+impl std::hash::Hash for BoundingBox {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.box_type.hash(state);
+        for point in self.coordinates.iter().flatten().flatten() {
+            point.to_bits().hash(state);
+        }
+    }
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn convert_global_objects() {
+    let timeline =
+        serde_json::from_str::<GlobalTimeline>(include_str!("../fixtures/search/adaptive.json"))
+            .unwrap();
+    let objects = timeline.global_objects;
+
+    let tweet = tweet::Tweet::try_from(&objects.tweets["1700000000000000002"]).unwrap();
+    assert_eq!(tweet.conversation_id, 1700000000000000001);
+    assert_eq!(tweet.posted_id, Some(3308337));
+    let data = match tweet.tweet_type {
+        tweet::TweetType::Tweet(data) => data,
+        other => panic!("expected a tweet, got {other:?}"),
+    };
+    assert_eq!(data.display_text_range, (0, 25));
+    assert_eq!(data.entry.hashtags, vec!["rustlang".to_string()]);
+    assert_eq!(data.entry.urls, vec!["https://rustacean.net".to_string()]);
+    assert_eq!(data.entry.media[0].id, 1700000000000000100);
+    assert_eq!(data.entry.media[0].ext_alt_text.as_deref(), Some("Ferris"));
+    assert_eq!(data.source, "Twitter Web App");
+    assert_eq!(data.stats.likes, 12);
+    assert_eq!(data.reply_info.replying_to, Some(1700000000000000001));
+
+    // without a range the whole text shows, counted in chars
+    let tweet = tweet::Tweet::try_from(&objects.tweets["1700000000000000005"]).unwrap();
+    match tweet.tweet_type {
+        tweet::TweetType::Tweet(data) => assert_eq!(data.display_text_range, (0, 18)),
+        other => panic!("expected a tweet, got {other:?}"),
+    }
+
+    assert!(matches!(
+        tweet::Tweet::try_from(&objects.tweets["1700000000000000006"]),
+        Err(TwitterBadTimeParse(_))
+    ));
+
+    let user = user::User::try_from(&objects.users["3308337"]).unwrap();
+    assert_eq!(user.name.handle, "crabbyrust");
+    assert_eq!(user.name.display, "Crabby");
+    assert_eq!(user.profile_stats.followers, 340);
+    assert_eq!(
+        user.additional_info.website.as_deref(),
+        Some("https://crabby.example.com")
+    );
+    assert_eq!(user.entities.description.hashtags[0].text, "rustlang");
+}
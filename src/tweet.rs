@@ -5,44 +5,53 @@ use crate::{
         SResult,
         TwtScrapeError::{BadJSONSchema, TwitterBadRestId, TwitterBadTimeParse},
     },
-    scrape::Scraper,
+    scrape::{
+        endpoints::Operation,
+        pagination::{collect_partial, paginate, Checkpoint, ItemError, Page, PartialResult},
+        Scraper,
+    },
     user::{Error, TwtUsrResult, User},
-    TwitterIdType,
+    FilterJSON, TwitterIdType,
 };
 use ahash::{HashSet, HashSetExt};
 use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
 use rkyv::Archive;
 #[cfg(feature = "scrape")]
 use scraper::{Html, Selector};
-use serde::{
-    de::{self, MapAccess, Visitor},
-    Deserialize, Deserializer, Serialize,
-};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::json;
 use std::hash::Hasher;
 use std::{
-    collections::{HashMap, VecDeque},
-    fmt::{self, Display, Write},
+    collections::HashMap,
+    fmt::{self, Display},
 };
 use tracing::warn;
 
 #[cfg(feature = "scrape")]
-static LINK_SELECTOR: Selector = Selector::parse("a").unwrap();
+pub(crate) const TWEET_CREATED_DATETIME: &str = "%a %b %d %T %z %Y";
 
+// `<a href="..." rel="nofollow">Twitter Web App</a>` -> `Twitter Web App`
 #[cfg(feature = "scrape")]
-pub(crate) const TWEET_CREATED_DATETIME: &str = "%a %b %d %T %z %Y";
+pub(crate) fn source_name(source: &str) -> String {
+    let frag = Html::parse_fragment(source);
+    Selector::parse("a")
+        .ok()
+        .and_then(|link| frag.select(&link).next().map(|elem| elem.inner_html()))
+        .unwrap_or_default()
+}
+
 pub fn twitter_request_url_thread(
-    handle: impl AsRef<str> + Display,
+    scraper: &Scraper,
+    id: impl Display,
     cursor: Option<impl AsRef<str> + Display>,
 ) -> String {
-    match cursor {
-        Some(crsr) => {
-            let crsr = urlencoding::encode(crsr.as_ref());
-            format!("https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail?variables=%7B%22focalTweetId%22%3A%22{handle}%22%2C%22cursor%22%3A%22{crsr}%22%2C%22referrer%22%3A%22messages%22%2C%22with_rux_injections%22%3Afalse%2C%22includePromotedContent%22%3Afalse%2C%22withCommunity%22%3Atrue%2C%22withQuickPromoteEligibilityTweetFields%22%3Atrue%2C%22withBirdwatchNotes%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%2C%22withVoice%22%3Atrue%2C%22withV2Timeline%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
-        None => {
-            format!("https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail?variables=%7B%22focalTweetId%22%3A%22{handle}%22%2C%22with_rux_injections%22%3Afalse%2C%22includePromotedContent%22%3Afalse%2C%22withCommunity%22%3Atrue%2C%22withQuickPromoteEligibilityTweetFields%22%3Atrue%2C%22withBirdwatchNotes%22%3Afalse%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%2C%22withVoice%22%3Atrue%2C%22withV2Timeline%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
+    let mut variables = json!({ "focalTweetId": id.to_string() });
+    if let Some(cursor) = cursor {
+        variables["cursor"] = cursor.as_ref().into();
+        variables["referrer"] = "messages".into();
     }
+    scraper.graphql_url(Operation::TweetDetail, variables)
 }
 
 #[derive(
@@ -57,57 +66,230 @@ pub struct Tweet {
 
 #[cfg(feature = "scrape")]
 impl Tweet {
+    // One TweetDetail request for just this tweet and its author, no scrolling.
+    // Tombstones come back with no author.
+    #[tracing::instrument]
+    pub async fn get(
+        scraper: &Scraper,
+        id: impl TwitterIdType + fmt::Debug,
+    ) -> SResult<(Tweet, Option<User>)> {
+        let tweet_id = id.to_u64()?;
+        let request = scraper
+            .api_req::<TweetRequest>(scraper.make_get_req(twitter_request_url_thread(
+                scraper,
+                tweet_id,
+                None::<&str>,
+            )))
+            .await?;
+
+        request.filter_json_err()?;
+
+        let results = request.focal_tweet(tweet_id).ok_or(BadJSONSchema(
+            "TweetRequest",
+            format!("No Tweet {tweet_id}"),
+        ))?;
+
+        let mut tweet = Tweet::new_from_entry(results)?;
+        let trr = match results {
+            TweetResults::Ok(trr) => trr,
+            TweetResults::Tombstone(_) => {
+                tweet.id = tweet_id;
+                return Ok((tweet, None));
+            }
+        };
+
+        let author = match User::from_result(scraper, trr.core.user_results.result.clone()).await {
+            Ok(user) => Some(user),
+            Err(why) => {
+                warn!(error = %why, tweet_id, "Failed to parse tweet author.");
+                None
+            }
+        };
+
+        Ok((tweet, author))
+    }
+
+    // Looks up many tweets, `concurrency` at a time, in the order of `ids`. Every request
+    // still goes through the scraper's delayer and in flight limit.
+    pub fn stream_many<'a>(
+        scraper: &'a Scraper,
+        ids: impl IntoIterator<Item = u64> + 'a,
+        concurrency: usize,
+    ) -> impl Stream<Item = (u64, SResult<(Tweet, Option<User>)>)> + 'a {
+        let mut seen = HashSet::new();
+        stream::iter(ids.into_iter().filter(move |id| seen.insert(*id)))
+            .map(move |id| async move { (id, Tweet::get(scraper, id).await) })
+            .buffered(concurrency.max(1))
+    }
+
+    pub async fn get_many(
+        scraper: &Scraper,
+        ids: impl IntoIterator<Item = u64>,
+        concurrency: usize,
+    ) -> TweetBatch {
+        let mut batch = TweetBatch::default();
+        let mut authors = HashSet::new();
+
+        let mut lookups = Box::pin(Self::stream_many(scraper, ids, concurrency));
+        while let Some((id, lookup)) = lookups.next().await {
+            match lookup {
+                Ok((tweet, author)) => {
+                    if let TweetType::Tombstone(text) = tweet.tweet_type {
+                        batch.tombstoned.push((id, text));
+                        continue;
+                    }
+                    batch.tweets.push(tweet);
+                    if let Some(user) = author {
+                        if authors.insert(user.id) {
+                            batch.users.push(user);
+                        }
+                    }
+                }
+                Err(why) => {
+                    warn!(error = %why, id, "Failed to look up tweet.");
+                    batch.unavailable.push((id, why.to_string()));
+                }
+            }
+        }
+
+        batch
+    }
+
     #[tracing::instrument]
     pub async fn parse_thread(
         scraper: &Scraper,
-        id: impl TwitterIdType + Display,
+        id: impl TwitterIdType + fmt::Debug,
     ) -> SResult<(Vec<Tweet>, Vec<User>)> {
-        let base_request = scraper
-            .api_req::<TweetRequest>(scraper.make_get_req(twitter_request_url_thread(&id, None)))
-            .await?;
+        Self::parse_thread_partial(scraper, id).await.into_result()
+    }
 
-        base_request.json_request_filter_errors()?;
+    // Keeps every tweet and user that parsed. The checkpoint records how far down the replies
+    // below the focal tweet the scroll got, the part of a conversation that runs for pages.
+    // Hand it to `parse_thread_from` to pick the replies back up.
+    #[tracing::instrument]
+    pub async fn parse_thread_partial(
+        scraper: &Scraper,
+        id: impl TwitterIdType + fmt::Debug,
+    ) -> PartialResult<(Vec<Tweet>, Vec<User>)> {
+        let mut result = PartialResult::new((vec![], vec![]), Checkpoint::default());
+
+        let base_request = match scraper
+            .api_req::<TweetRequest>(scraper.make_get_req(twitter_request_url_thread(
+                scraper,
+                &id,
+                None::<&str>,
+            )))
+            .await
+            .and_then(|request| request.filter_json_err().map(|_| request))
+        {
+            Ok(request) => request,
+            Err(why) => {
+                result.failure = Some(why);
+                return result;
+            }
+        };
 
         let mut requests = Vec::with_capacity(5);
 
         // lets first get the conversation id
-        let conversation_id = base_request
+        let conversation_id = match base_request
             .first_tweet()
-            .ok_or(BadJSONSchema("TweetRequest", "No First Tweet".to_string()))?
-            .legacy
-            .conversation_id_str
-            .parse::<u64>()
-            .map_err(|why| TwitterBadRestId("Conversation RestID", why.to_string()))?;
-
-        if let Some(cursor) = base_request.filter_cursor(FilterCursorTweetRequest::Top) {
-            requests.append(
-                &mut TweetRequest::scroll(
-                    scraper,
-                    &id,
-                    cursor.to_string(),
-                    FilterCursorTweetRequest::Top,
-                )
-                .await?,
-            )
+            .ok_or(BadJSONSchema("TweetRequest", "No First Tweet".to_string()))
+            .and_then(|first| {
+                first
+                    .legacy
+                    .conversation_id_str
+                    .parse::<u64>()
+                    .map_err(|why| TwitterBadRestId("Conversation RestID", why.to_string()))
+            }) {
+            Ok(conversation_id) => conversation_id,
+            Err(why) => {
+                result.failure = Some(why);
+                return result;
+            }
+        };
+
+        let top = base_request
+            .filter_cursor(FilterCursorTweetRequest::Top)
+            .map(str::to_string);
+        let bottom = base_request
+            .filter_cursor(FilterCursorTweetRequest::Bottom)
+            .map(str::to_string);
+
+        if let Some(cursor) = top {
+            let start = Checkpoint::at(cursor);
+            let pages =
+                TweetRequest::scroll(scraper, &id, start.clone(), FilterCursorTweetRequest::Top);
+            let mut above = collect_partial(pages, start).await;
+            // scrolling up walks away from the focal tweet, oldest page comes last
+            above.data.reverse();
+            requests.append(&mut above.data);
+            result.failure = above.failure;
         }
 
         requests.push(base_request);
 
-        if let Some(cursor) = base_request.filter_cursor(FilterCursorTweetRequest::Bottom) {
-            requests.append(
-                &mut TweetRequest::scroll(
-                    scraper,
-                    &id,
-                    cursor.to_string(),
-                    FilterCursorTweetRequest::Bottom,
-                )
-                .await?
-                .into(),
-            )
+        if let (Some(cursor), None) = (bottom, &result.failure) {
+            let start = Checkpoint::at(cursor);
+            let pages = TweetRequest::scroll(
+                scraper,
+                &id,
+                start.clone(),
+                FilterCursorTweetRequest::Bottom,
+            );
+            let mut below = collect_partial(pages, start).await;
+            requests.append(&mut below.data);
+            result.checkpoint = below.checkpoint;
+            result.failure = below.failure;
+        } else if result.failure.is_none() {
+            result.checkpoint.finished = true;
+        }
+
+        Self::collect_thread(scraper, &id, Some(conversation_id), requests, &mut result).await;
+        result
+    }
+
+    // Carries on down the replies from the checkpoint of an earlier `parse_thread_partial` or
+    // `parse_thread_from`. Only what's below the checkpoint is returned.
+    #[tracing::instrument]
+    pub async fn parse_thread_from(
+        scraper: &Scraper,
+        id: impl TwitterIdType + fmt::Debug,
+        checkpoint: Checkpoint,
+    ) -> PartialResult<(Vec<Tweet>, Vec<User>)> {
+        if checkpoint.cursor.is_none() && !checkpoint.finished {
+            return Self::parse_thread_partial(scraper, id).await;
         }
 
-        let mut tweets = Vec::with_capacity(requests.len() * 10);
-        let mut users = Vec::with_capacity(requests.len() * 10);
+        let pages = TweetRequest::scroll(
+            scraper,
+            &id,
+            checkpoint.clone(),
+            FilterCursorTweetRequest::Bottom,
+        );
+        let below = collect_partial(pages, checkpoint).await;
+        let mut result = PartialResult::new((vec![], vec![]), below.checkpoint);
+        result.failure = below.failure;
+
+        // pages of replies may not have the focal tweet, each reply keeps its own conversation id
+        Self::collect_thread(scraper, &id, None, below.data, &mut result).await;
+        result
+    }
+
+    // Parses the tweets and their authors out of a conversation's pages into `result`. A
+    // `conversation_id` is set on every tweet, otherwise each keeps the one it was parsed with.
+    async fn collect_thread(
+        scraper: &Scraper,
+        id: &(impl Display + fmt::Debug),
+        conversation_id: Option<u64>,
+        requests: Vec<TweetRequest>,
+        result: &mut PartialResult<(Vec<Tweet>, Vec<User>)>,
+    ) {
+        let (tweets, users) = &mut result.data;
+        let errors = &mut result.errors;
+        tweets.reserve(requests.len() * 10);
+        users.reserve(requests.len() * 10);
+        // rest ids of the authors parsed so far
         let mut already_parsed_users = HashSet::with_capacity(10);
 
         for request in requests {
@@ -120,23 +302,31 @@ impl Tweet {
                     for entry in add.entries {
                         match entry.content {
                             EntryVariant::Tweet(twt) => {
-                                let mut tweet =
-                                    Tweet::new_from_entry(&twt.item_content.tweet_results)?;
+                                let results = &twt.item_content.tweet_results;
+                                let mut tweet = match Tweet::new_from_entry(results) {
+                                    Ok(tweet) => tweet,
+                                    Err(why) => {
+                                        warn!(error = %why, id = %id, "Failed to parse tweet. Skipping...");
+                                        let item = results.rest_id().unwrap_or(&entry.entry_id);
+                                        errors.push(ItemError::new(item, &why));
+                                        continue;
+                                    }
+                                };
 
                                 if tweet.id == 0 {
                                     let twttid = match entry
                                         .entry_id
                                         .split("-")
                                         .nth(1)
-                                        .map(|x| x.parse::<u64>().ok())
-                                        .flatten()
-                                        .ok_or(IdParseError(format!("Graveyard Tweet No Entry ID")))
-                                    {
+                                        .and_then(|x| x.parse::<u64>().ok())
+                                        .ok_or(IdParseError(
+                                            "Graveyard Tweet No Entry ID".to_string(),
+                                        )) {
                                         Ok(id) => id,
                                         Err(why) => {
                                             warn!(
-                                                error = why,
-                                                id = id,
+                                                error = %why,
+                                                id = %id,
                                                 "No ID, leaving orphan tree(s)"
                                             );
                                             continue;
@@ -144,34 +334,58 @@ impl Tweet {
                                     };
                                     tweet.id = twttid;
                                 }
-                                tweet.conversation_id = conversation_id;
+                                if let Some(conversation_id) = conversation_id {
+                                    tweet.conversation_id = conversation_id;
+                                }
                                 tweets.push(tweet);
 
                                 if let TweetResults::Ok(trr) = twt.item_content.tweet_results {
                                     if let TwtUsrResult::User(user) = &trr.core.user_results.result
                                     {
-                                        if already_parsed_users.contains(&user.id) {
+                                        if already_parsed_users.contains(&user.rest_id) {
                                             continue;
                                         }
                                     }
-                                    let user =
-                                        User::from_result(scraper, trr.core.user_results.result)
-                                            .await?;
-                                    already_parsed_users.insert(user.id.to_string());
-                                    users.push(user)
+                                    let user_results = &trr.core.user_results.result;
+                                    let rest_id = user_results
+                                        .rest_id()
+                                        .unwrap_or(&entry.entry_id)
+                                        .to_string();
+                                    match User::from_result(scraper, trr.core.user_results.result)
+                                        .await
+                                    {
+                                        Ok(user) => {
+                                            already_parsed_users.insert(user.id.to_string());
+                                            users.push(user)
+                                        }
+                                        Err(why) => {
+                                            warn!(error = %why, id = %id, "Failed to parse user. Skipping...");
+                                            errors.push(ItemError::new(rest_id, &why));
+                                        }
+                                    }
                                 }
                             }
                             EntryVariant::ConversationThread(ct) => {
                                 for thread in ct.content.items {
-                                    let mut tweet = Tweet::new_from_entry(
-                                        &thread.item.item_content.tweet_results,
-                                    )?;
+                                    let results = &thread.item.item_content.tweet_results;
+                                    let mut tweet = match Tweet::new_from_entry(results) {
+                                        Ok(tweet) => tweet,
+                                        Err(why) => {
+                                            warn!(error = %why, id = %id, "Failed to parse tweet. Skipping...");
+                                            let item =
+                                                results.rest_id().unwrap_or(&thread.entry_id);
+                                            errors.push(ItemError::new(item, &why));
+                                            continue;
+                                        }
+                                    };
 
                                     if tweet.id == 0 {
                                         warn!("Deleted Tweet, generating orphans...");
                                         continue;
                                     }
-                                    tweet.conversation_id = conversation_id;
+                                    if let Some(conversation_id) = conversation_id {
+                                        tweet.conversation_id = conversation_id;
+                                    }
                                     tweets.push(tweet);
 
                                     if let TweetResults::Ok(trr) =
@@ -180,18 +394,31 @@ impl Tweet {
                                         if let TwtUsrResult::User(usr) =
                                             &trr.core.user_results.result
                                         {
-                                            if already_parsed_users.contains(&usr.id) {
+                                            if already_parsed_users.contains(&usr.rest_id) {
                                                 continue;
                                             }
                                         }
 
-                                        let user = User::from_result(
+                                        let user_results = &trr.core.user_results.result;
+                                        let rest_id = user_results
+                                            .rest_id()
+                                            .unwrap_or(&thread.entry_id)
+                                            .to_string();
+                                        match User::from_result(
                                             scraper,
                                             trr.core.user_results.result,
                                         )
-                                        .await?;
-                                        already_parsed_users.insert(user.id.to_string());
-                                        users.push(user)
+                                        .await
+                                        {
+                                            Ok(user) => {
+                                                already_parsed_users.insert(user.id.to_string());
+                                                users.push(user)
+                                            }
+                                            Err(why) => {
+                                                warn!(error = %why, id = %id, "Failed to parse user. Skipping...");
+                                                errors.push(ItemError::new(rest_id, &why));
+                                            }
+                                        }
                                     }
                                 }
                             }
@@ -204,8 +431,6 @@ impl Tweet {
 
         tweets.shrink_to_fit();
         users.shrink_to_fit();
-
-        Ok((tweets, users))
     }
 
     /// HEY FUTURE ASS MF!!!
@@ -251,7 +476,7 @@ impl Tweet {
                 let edit_ids = trr
                     .edit_control
                     .edit_tweet_ids
-                    .into_iter()
+                    .iter()
                     .map(|id| {
                         id.parse::<u64>()
                             .map_err(|why| TwitterBadRestId("Tweet RestID", why.to_string()))
@@ -262,7 +487,7 @@ impl Tweet {
                     .legacy
                     .extended_entities
                     .media
-                    .into_iter()
+                    .iter()
                     .map(|x| {
                         let media_id = x
                             .id_str
@@ -270,12 +495,12 @@ impl Tweet {
                             .map_err(|why| TwitterBadRestId("Tweet RestID", why.to_string()))?;
                         Ok(Media {
                             id: media_id,
-                            media_key: x.media_key,
-                            media_url_https: x.media_url_https,
-                            r#type: x.r#type,
-                            ext_alt_text: x.ext_alt_text,
-                            views: x.media_stats.map(|x| x.view_count),
-                            expanded_url: x.expanded_url,
+                            media_key: x.media_key.clone(),
+                            media_url_https: x.media_url_https.clone(),
+                            r#type: x.r#type.clone(),
+                            ext_alt_text: x.ext_alt_text.clone(),
+                            views: x.media_stats.as_ref().map(|x| x.view_count),
+                            expanded_url: x.expanded_url.clone(),
                         })
                     })
                     .collect::<SResult<Vec<Media>>>()?;
@@ -284,33 +509,33 @@ impl Tweet {
                     .legacy
                     .entities
                     .urls
-                    .into_iter()
-                    .map(|url| url.expanded_url)
+                    .iter()
+                    .map(|url| url.expanded_url.clone())
                     .collect::<Vec<String>>();
 
                 let hashtags = trr
                     .legacy
                     .entities
                     .hashtags
-                    .into_iter()
-                    .map(|ht| ht.text)
+                    .iter()
+                    .map(|ht| ht.text.clone())
                     .collect::<Vec<String>>();
 
-                let card = trr.card.map(|tcd| Card {
-                    id: tcd.rest_id,
-                    url: tcd.legacy.url,
-                    name: tcd.legacy.name,
+                let card = trr.card.as_ref().map(|tcd| Card {
+                    id: tcd.rest_id.clone(),
+                    url: tcd.legacy.url.clone(),
+                    name: tcd.legacy.name.clone(),
                     values: tcd
                         .legacy
                         .binding_values
-                        .into_iter()
-                        .map(|bv| (bv.key, bv.value))
+                        .iter()
+                        .map(|bv| (bv.key.clone(), bv.value.clone()))
                         .collect::<HashMap<String, CardValue, ahash::RandomState>>(),
                 });
 
                 let display_text_range = {
                     if trr.legacy.display_text_range.len() != 2 {
-                        (0, trr.legacy.full_text.len() as u16)
+                        (0, trr.legacy.full_text.chars().count() as u16)
                     } else {
                         (
                             trr.legacy.display_text_range[0],
@@ -353,27 +578,17 @@ impl Tweet {
                     }
                 };
 
-                let source = {
-                    let frag = Html::parse_fragment(&trr.legacy.source);
-                    frag.select(&LINK_SELECTOR)
-                        .next()
-                        .map(|elem| elem.inner_html())
-                        .unwrap_or_default()
-                };
+                let source = source_name(&trr.legacy.source);
 
-                let collaborator_ctrl = trr
-                    .legacy
-                    .collab_control
-                    .map(|x| {
-                        x.collaborators_results
-                            .into_iter()
-                            .map(|x| match x.result {
-                                TwtUsrResult::User(u) => u.rest_id.parse::<u64>().ok(),
-                                TwtUsrResult::UserUnavailable(_) => None,
-                            })
-                            .collect::<Option<Vec<_>>>()
-                    })
-                    .flatten();
+                let collaborator_ctrl = trr.legacy.collab_control.as_ref().and_then(|x| {
+                    x.collaborators_results
+                        .iter()
+                        .map(|x| match &x.result {
+                            TwtUsrResult::User(u) => u.rest_id.parse::<u64>().ok(),
+                            TwtUsrResult::UserUnavailable(_) => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                });
 
                 Ok(Tweet {
                     id,
@@ -404,10 +619,10 @@ impl Tweet {
                         },
                         moderated: false,
                         conversation_control: ConversationControl::None,
-                        vibe: trr.vibe.map(|v| Vibe {
-                            discovery_query_text: v.discovery_query_text,
-                            text: v.text,
-                            img_description: v.img_description,
+                        vibe: trr.vibe.as_ref().map(|v| Vibe {
+                            discovery_query_text: v.discovery_query_text.clone(),
+                            text: v.text.clone(),
+                            img_description: v.img_description.clone(),
                         }),
                         place: trr.legacy.place.clone(),
                         collaborators: collaborator_ctrl,
@@ -424,6 +639,37 @@ impl Tweet {
     }
 }
 
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct TweetBatch {
+    pub tweets: Vec<Tweet>,
+    // each author once
+    pub users: Vec<User>,
+    // id and the tombstone's text
+    pub tombstoned: Vec<(u64, String)>,
+    // id and why it couldn't be fetched
+    pub unavailable: Vec<(u64, String)>,
+}
+
+// `values` has no order to hash in, the card's id already tells cards apart
+impl std::hash::Hash for Card {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.url.hash(state);
+        self.name.hash(state);
+    }
+}
+
 impl std::hash::Hash for Tweet {
     fn hash<H: Hasher>(&self, state: &mut H) {
         if self.id != 0 {
@@ -569,16 +815,7 @@ pub struct ReplyInfo {
 }
 
 #[derive(
-    Clone,
-    Debug,
-    Hash,
-    PartialEq,
-    Eq,
-    Serialize,
-    Deserialize,
-    Archive,
-    rkyv::Serialize,
-    rkyv::Deserialize,
+    Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
 )]
 pub struct Card {
     pub id: String,
@@ -641,78 +878,80 @@ pub struct Media {
     rkyv::Deserialize,
 )]
 pub(crate) struct TweetRequest {
+    #[serde(default)]
     pub(crate) errors: Vec<Error>,
+    // empty when twitter only sent errors, e.g. for a deleted tweet
+    #[serde(default)]
     pub(crate) data: Data,
 }
 
 #[cfg(feature = "scrape")]
 impl TweetRequest {
-    pub(crate) fn first_tweet(&self) -> Option<&TweetResultResult> {
-        for inst in self
-            .data
+    fn tweet_entries(&self) -> impl Iterator<Item = (&str, &TweetResults)> {
+        self.data
             .threaded_conversation_with_injections_v2
             .instructions
-        {
-            if let Instruction::TimelineAddEntries(add) = inst {
-                for entry in &add.entries {
-                    if let Entry::Tweet(te) = entry {
-                        if let TweetResults::Ok(trr) = &te.item_content.tweet_results {
-                            Some(trr)
-                        }
-                    }
+            .iter()
+            .filter_map(|inst| match inst {
+                Instruction::TimelineAddEntries(add) => Some(&add.entries),
+                Instruction::TimelineTerminateTimeline(_) => None,
+            })
+            .flatten()
+            .filter_map(|entry| match &entry.content {
+                EntryVariant::Tweet(te) => {
+                    Some((entry.entry_id.as_str(), &te.item_content.tweet_results))
                 }
-            }
-        }
+                _ => None,
+            })
+    }
 
-        None
+    pub(crate) fn first_tweet(&self) -> Option<&TweetResultResult> {
+        self.tweet_entries().find_map(|(_, results)| match results {
+            TweetResults::Ok(trr) => Some(trr.as_ref()),
+            TweetResults::Tombstone(_) => None,
+        })
+    }
+
+    // the requested tweet, ancestors of a reply come before it in the timeline
+    pub(crate) fn focal_tweet(&self, id: u64) -> Option<&TweetResults> {
+        let entry_id = format!("tweet-{id}");
+        let rest_id = id.to_string();
+        self.tweet_entries()
+            .find(|(entry, _)| *entry == entry_id)
+            .or_else(|| {
+                self.tweet_entries()
+                    .find(|(_, results)| results.rest_id() == Some(rest_id.as_str()))
+            })
+            .map(|(_, results)| results)
     }
 
     pub(crate) fn filter_cursor(&self, filter: FilterCursorTweetRequest) -> Option<&str> {
-        for inst in &self
-            .data
+        self.data
             .threaded_conversation_with_injections_v2
             .instructions
-        {
-            inst.filter_cursor(filter)
-        }
-
-        None
+            .iter()
+            .find_map(|inst| inst.filter_cursor(filter))
     }
 
-    #[tracing::instrument]
-    pub(crate) async fn scroll(
-        scraper: &Scraper,
-        id: impl TwitterIdType + Display,
-        first_cursor: String,
+    // pages of the conversation in one direction, resuming from `start`
+    pub(crate) fn scroll<'a>(
+        scraper: &'a Scraper,
+        id: impl Display + 'a,
+        start: Checkpoint,
         filter: FilterCursorTweetRequest,
-    ) -> SResult<VecDeque<Self>> {
-        let mut requests = VecDeque::with_capacity(5);
-
-        let mut cursor_counter = first_cursor.to_string();
-        let mut break_on_next = false;
-        loop {
-            let scrolled_up_request = scraper
-                .api_req::<TweetRequest>(
-                    scraper.make_get_req(twitter_request_url_thread(&id, Some(&cursor_counter))),
-                )
-                .await?;
-
-            scrolled_up_request.json_request_filter_errors()?;
-
-            requests.push_front(scrolled_up_request);
-            if break_on_next {
-                break;
-            }
-
-            match scrolled_up_request.filter_cursor(filter) {
-                Some(up) => {
-                    cursor_counter = up.to_string();
-                }
-                None => break_on_next = true,
+    ) -> impl Stream<Item = SResult<Page<Self>>> + 'a {
+        paginate(start, move |cursor| {
+            let url = twitter_request_url_thread(scraper, &id, cursor);
+            async move {
+                let request = scraper
+                    .api_req::<TweetRequest>(scraper.make_get_req(url))
+                    .await?;
+                request.filter_json_err()?;
+
+                let next_cursor = request.filter_cursor(filter).map(str::to_string);
+                Ok(Page::new(vec![request], next_cursor))
             }
-        }
-
-        Ok(requests)
+        })
     }
 }
 
@@ -727,6 +966,7 @@ pub enum FilterCursorTweetRequest {
 #[derive(
     Clone,
     Debug,
+    Default,
     Hash,
     PartialEq,
     Eq,
@@ -737,12 +977,14 @@ pub enum FilterCursorTweetRequest {
     rkyv::Deserialize,
 )]
 pub(crate) struct Data {
+    #[serde(default)]
     pub(crate) threaded_conversation_with_injections_v2: ThreadedConversation,
 }
 
 #[derive(
     Clone,
     Debug,
+    Default,
     Hash,
     PartialEq,
     Eq,
@@ -778,7 +1020,7 @@ impl Instruction {
     pub(crate) fn filter_cursor(&self, cursor: FilterCursorTweetRequest) -> Option<&str> {
         if let Instruction::TimelineAddEntries(add) = self {
             for entry in &add.entries {
-                if let Entry::Cursor(c) = entry {
+                if let EntryVariant::Cursor(c) = &entry.content {
                     match cursor {
                         FilterCursorTweetRequest::Top => {
                             if c.entry_id.starts_with("cursor-top") {
@@ -816,114 +1058,57 @@ pub(crate) struct TimelineAddEntries {
     pub entries: Vec<Entry>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[derive(
+    Clone, Debug, Hash, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
 pub(crate) struct Entry {
     #[serde(rename = "entryId")]
     pub entry_id: String,
     pub content: EntryVariant,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[derive(
+    Clone, Debug, Hash, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
 pub(crate) enum EntryVariant {
     Tweet(TweetEnt),
     ConversationThread(ConversationThread),
     Cursor(Cursor),
 }
 
+// The kind of entry is only told by its id. Tweets are read from the entry's `content`,
+// threads and cursors from the whole entry.
 impl<'de> Deserialize<'de> for Entry {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        enum Field {
-            EntryId,
-            SortId,
-            Content,
-        }
-
-        impl<'de> Deserialize<'de> for Field {
-            fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
-            where
-                D: Deserializer<'de>,
-            {
-                struct FieldVisitor;
-
-                impl<'de> Visitor<'de> for FieldVisitor {
-                    type Value = Field;
-
-                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("entry type sort content")
-                    }
-
-                    fn visit_str<E>(self, value: &str) -> Result<Field, E>
-                    where
-                        E: de::Error,
-                    {
-                        match value {
-                            "entryId" => Ok(Field::EntryId),
-                            "sortId" => Ok(Field::SortId),
-                            "content" => Ok(Field::Content),
-                            _ => Err(de::Error::unknown_variant(value, VARIANTS)),
-                        }
-                    }
-                }
-
-                deserializer.deserialize_identifier(FieldVisitor)
-            }
-        }
-
-        struct EntryVisitor;
-
-        impl<'de> Visitor<'de> for EntryVisitor {
-            type Value = Entry;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("enum Entry")
-            }
-
-            fn visit_map<V>(self, mut map: V) -> Result<Entry, V::Error>
-            where
-                V: MapAccess<'de>,
-            {
-                let mut entry_id: Option<String> = None;
-                let mut __typename: Option<String> = None;
-                let mut sort_id: Option<String> = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::EntryId => {
-                            entry_id = Some(map.next_value()?);
-                        }
-                        Field::SortId => {
-                            sort_id = Some(map.next_value()?);
-                        }
-                        Field::Content => {
-                            if let Some(entry) = &entry_id {
-                                if entry.starts_with("tweet-") {
-                                    Ok(EntryVariant::Tweet(map.next_value()?))
-                                } else if entry.starts_with("conversationthread-") {
-                                    Ok(EntryVariant::ConversationThread(map.next_value()?))
-                                } else if entry.starts_with("cursor-") {
-                                    Ok(EntryVariant::Cursor(map.next_value()?))
-                                } else {
-                                    Err(de::Error::unknown_variant(
-                                        entry,
-                                        &["tweet", "conversationthread", "cursor"],
-                                    ))
-                                }
-                            }
-                            Err(de::Error::unknown_variant(
-                                "None",
-                                &["tweet", "conversationthread", "cursor"],
-                            ))
-                        }
-                    }
-                }
-                Err(de::Error::missing_field("content"))
-            }
+        let entry = serde_json::Value::deserialize(deserializer)?;
+        let entry_id = entry
+            .get("entryId")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| de::Error::missing_field("entryId"))?
+            .to_string();
+
+        let content = if entry_id.starts_with("tweet-") {
+            let content = entry
+                .get("content")
+                .cloned()
+                .ok_or_else(|| de::Error::missing_field("content"))?;
+            serde_json::from_value(content).map(EntryVariant::Tweet)
+        } else if entry_id.starts_with("conversationthread-") {
+            serde_json::from_value(entry).map(EntryVariant::ConversationThread)
+        } else if entry_id.starts_with("cursor-") {
+            serde_json::from_value(entry).map(EntryVariant::Cursor)
+        } else {
+            return Err(de::Error::unknown_variant(
+                &entry_id,
+                &["tweet", "conversationthread", "cursor"],
+            ));
         }
+        .map_err(de::Error::custom)?;
 
-        const VARIANTS: &[&str] = &["Tweet", "ConversationThread", "Cursor"];
-        deserializer.deserialize_map(EntryVisitor {})
+        Ok(Entry { entry_id, content })
     }
 }
 
@@ -1086,10 +1271,22 @@ pub(crate) struct EditControl {
 )]
 #[serde(tag = "__typename")]
 pub(crate) enum TweetResults {
-    Ok(TweetResultResult),
+    #[serde(rename = "Tweet")]
+    Ok(Box<TweetResultResult>),
+    #[serde(rename = "TweetTombstone")]
     Tombstone(TweetTombstone),
 }
 
+impl TweetResults {
+    // names the tweet in error reports, tombstones only have their timeline entry id
+    pub(crate) fn rest_id(&self) -> Option<&str> {
+        match self {
+            TweetResults::Ok(trr) => Some(&trr.rest_id),
+            TweetResults::Tombstone(_) => None,
+        }
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -1271,6 +1468,7 @@ pub(crate) struct TweetLegacy {
     pub conversation_id_str: String,
     pub entities: TweetEntry,
     pub extended_entities: TweetExtEntry,
+    #[serde(rename = "favorite_count")]
     pub favourite_count: u32,
     pub is_quote_status: bool,
     pub possibly_sensitive: bool,
@@ -1586,3 +1784,252 @@ pub(crate) struct CursorItemContent {
 pub(crate) struct TimelineTerminateTimeline {
     pub direction: String,
 }
+
+#[cfg(feature = "scrape")]
+#[test]
+fn get_tweet_from_fixture() {
+    use crate::error::TwtScrapeError;
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    let detail = "https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail";
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(detail, include_str!("../fixtures/tweet/tweet_detail.json")),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // the reply's parent comes first in the timeline, the focal tweet is still picked
+        let (tweet, author) = Tweet::get(&scraper, 1700000000000000002).await.unwrap();
+        assert_eq!(tweet.id, 1700000000000000002);
+        assert_eq!(tweet.conversation_id, 1700000000000000001);
+        assert_eq!(tweet.posted_id, Some(3308337));
+        let data = match &tweet.tweet_type {
+            TweetType::Tweet(data) => data,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(data.text, "Even the #rustlang mascot https://t.co/cr4b");
+        assert_eq!(data.source, "Twitter Web App");
+        assert_eq!(data.entry.hashtags, vec!["rustlang"]);
+        assert_eq!(data.entry.urls, vec!["https://rustacean.net"]);
+        assert_eq!(data.entry.media[0].views, Some(77));
+        assert_eq!(data.stats.likes, 12);
+        assert_eq!(data.reply_info.replying_to, Some(1700000000000000001));
+        assert_eq!(author.unwrap().name.handle, "crabbyrust");
+
+        // tweets in the timeline but not the focal one aren't mixed up with it
+        assert!(matches!(
+            Tweet::get(&scraper, 1700000000000000404).await,
+            Err(TwtScrapeError::BadJSONSchema(..))
+        ));
+    });
+
+    block_on(async {
+        let transport = Arc::new(FixtureTransport::new().with_json(
+            detail,
+            include_str!("../fixtures/tweet/tweet_tombstone.json"),
+        ));
+        let scraper = fixture_scraper(&transport).await;
+
+        let (tweet, author) = Tweet::get(&scraper, 1700000000000000009).await.unwrap();
+        assert_eq!(tweet.id, 1700000000000000009);
+        assert_eq!(
+            tweet.tweet_type,
+            TweetType::Tombstone(
+                "This Tweet was deleted by the Tweet author. Learn more".to_string()
+            )
+        );
+        assert!(author.is_none());
+    });
+
+    block_on(async {
+        let transport = Arc::new(FixtureTransport::new().with_json(
+            detail,
+            r#"{"errors":[{"message":"_Missing: No status found with that ID.","code":144}],"data":{}}"#,
+        ));
+        let scraper = fixture_scraper(&transport).await;
+
+        assert!(matches!(
+            Tweet::get(&scraper, 1700000000000000404).await,
+            Err(TwtScrapeError::TwitterJSONError(144, _))
+        ));
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn get_many_tweets() {
+    use crate::scrape::transport::{block_on, fixture_scraper, ApiResponse, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(FixtureTransport::new());
+        let scraper = fixture_scraper(&transport).await;
+        let answer = |id: u64, body: &str| {
+            let url = twitter_request_url_thread(&scraper, id, None::<&str>);
+            let response = ApiResponse::new(url.clone(), 200, body)
+                .with_header("content-type", "application/json");
+            transport.push_response(url, response);
+        };
+        let detail = include_str!("../fixtures/tweet/tweet_detail.json");
+        answer(1700000000000000001, detail);
+        answer(1700000000000000002, detail);
+        answer(
+            1700000000000000009,
+            include_str!("../fixtures/tweet/tweet_tombstone.json"),
+        );
+        answer(
+            1700000000000000404,
+            r#"{"errors":[{"message":"_Missing: No status found with that ID.","code":144}]}"#,
+        );
+
+        let batch = Tweet::get_many(
+            &scraper,
+            [
+                1700000000000000002,
+                1700000000000000009,
+                1700000000000000002,
+                1700000000000000404,
+                1700000000000000001,
+            ],
+            2,
+        )
+        .await;
+
+        // repeated ids are only looked up once
+        let detail_url = "https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail";
+        assert_eq!(transport.request_count(detail_url), 4);
+        let ids = batch
+            .tweets
+            .iter()
+            .map(|tweet| tweet.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1700000000000000002, 1700000000000000001]);
+        // both are by the same author
+        assert_eq!(batch.users.len(), 1);
+        assert_eq!(
+            batch.tombstoned,
+            vec![(
+                1700000000000000009,
+                "This Tweet was deleted by the Tweet author. Learn more".to_string()
+            )]
+        );
+        let unavailable = batch
+            .unavailable
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        assert_eq!(unavailable, vec![1700000000000000404]);
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn stream_many_tweets_concurrently() {
+    use crate::scrape::transport::{
+        block_on, ApiRequest, FixtureTransport, Transport, TransportFuture,
+    };
+    use crate::scrape::ScraperBuilder;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // answers like `inner`, a little later, counting how many requests overlap
+    #[derive(Debug)]
+    struct Slow {
+        inner: FixtureTransport,
+        in_flight: AtomicUsize,
+        most: AtomicUsize,
+    }
+
+    impl Transport for Slow {
+        fn execute(&self, request: ApiRequest) -> TransportFuture<'_> {
+            Box::pin(async move {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                self.inner.execute(request).await
+            })
+        }
+    }
+
+    block_on(async {
+        let transport = Arc::new(Slow {
+            inner: FixtureTransport::new().with_guest_token("1234").with_json(
+                "https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail",
+                include_str!("../fixtures/tweet/tweet_tombstone.json"),
+            ),
+            in_flight: AtomicUsize::new(0),
+            most: AtomicUsize::new(0),
+        });
+        let scraper = ScraperBuilder::new()
+            .with_transport(transport.clone())
+            .finish()
+            .await
+            .unwrap();
+
+        let ids = (1..=9).map(|n| 1700000000000000000 + n).collect::<Vec<_>>();
+        let looked_up = Tweet::stream_many(&scraper, ids.clone(), 3)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>()
+            .await;
+        // in the order asked for, never more than 3 at once
+        assert_eq!(looked_up, ids);
+        assert_eq!(transport.most.load(Ordering::SeqCst), 3);
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn resume_thread_from_checkpoint() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    let detail = "https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail";
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(detail, include_str!("../fixtures/tweet/tweet_detail.json"))
+                .with_json(
+                    detail,
+                    r#"{"errors":[{"message":"Over capacity","code":130}],"data":{}}"#,
+                )
+                .with_json(
+                    detail,
+                    include_str!("../fixtures/tweet/tweet_detail_replies.json"),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // the second page of replies fails, the checkpoint stays on its cursor
+        let first = Tweet::parse_thread_partial(&scraper, 1700000000000000002).await;
+        assert!(first.failure.is_some());
+        assert_eq!(first.checkpoint.cursor.as_deref(), Some("bottom-1"));
+        let ids = first
+            .data
+            .0
+            .iter()
+            .map(|tweet| tweet.id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                1700000000000000001,
+                1700000000000000002,
+                1700000000000000003
+            ]
+        );
+
+        // the page picked back up has no focal tweet, its reply keeps its conversation
+        let rest = Tweet::parse_thread_from(&scraper, 1700000000000000002, first.checkpoint).await;
+        assert!(rest.failure.is_none());
+        assert!(rest.checkpoint.finished);
+        let (tweets, users) = rest.data;
+        assert_eq!(tweets.len(), 1);
+        assert_eq!(tweets[0].id, 1700000000000000004);
+        assert_eq!(tweets[0].conversation_id, 1700000000000000001);
+        assert_eq!(users[0].name.handle, "crabbyrust");
+    });
+}
@@ -1,15 +1,21 @@
 use crate::as_option;
 use crate::error::SResult;
-use crate::error::TwtScrapeError::{
-    TwitterBadRestId, TwitterBadTimeParse, TwitterJSONError, UserResultError,
-};
+use crate::error::TwtScrapeError;
+use crate::error::TwtScrapeError::{TwitterBadRestId, TwitterBadTimeParse, TwitterJSONError};
 #[cfg(feature = "scrape")]
-use crate::scrape::Scraper;
+use crate::scrape::endpoints::Operation;
+#[cfg(feature = "scrape")]
+use crate::scrape::{Scraper, WebsiteResolution};
+use ahash::HashSet;
 use chrono::{DateTime, Utc};
 use rkyv::Archive;
-use serde::{Deserialize, Deserializer, Serialize};
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "scrape")]
+use serde_json::json;
+use std::fmt::Debug;
 use std::hash::Hasher;
+#[cfg(feature = "scrape")]
+use tracing::warn;
 
 #[cfg(feature = "scrape")]
 pub const TWITTER_IGNORE_ERROR_CODE: i32 = 37;
@@ -18,10 +24,28 @@ pub const TWITTER_IGNORE_ERROR_CODE: i32 = 37;
 pub const JOINDATE_PARSE_STR: &str = "%a %b %d %T %z %Y";
 
 #[cfg(feature = "scrape")]
-pub fn twitter_request_url_handle(handle: &str) -> String {
-    format!("https://twitter.com/i/api/graphql/ptQPCD7NrFS_TW71Lq07nw/UserByScreenName?variables%3D%7B%22screen_name%22%3A%22{handle}%22%2C%22withSafetyModeUserFields%22%3Atrue%2C%22withSuperFollowsUserFields%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%7D")
+pub fn twitter_request_url_handle(scraper: &Scraper, handle: &str) -> String {
+    scraper.graphql_url(
+        Operation::UserByScreenName,
+        json!({ "screen_name": handle }),
+    )
+}
+
+#[cfg(feature = "scrape")]
+pub fn twitter_request_url_rest_id(scraper: &Scraper, id: u64) -> String {
+    scraper.graphql_url(Operation::UserByRestId, json!({ "userId": id.to_string() }))
 }
 
+#[cfg(feature = "scrape")]
+pub fn twitter_request_url_rest_ids(scraper: &Scraper, ids: &[u64]) -> String {
+    let ids = ids.iter().map(u64::to_string).collect::<Vec<_>>();
+    scraper.graphql_url(Operation::UsersByRestIds, json!({ "userIds": ids }))
+}
+
+// UsersByRestIds turns away bigger batches
+#[cfg(feature = "scrape")]
+pub const USERS_BY_REST_IDS_MAX: usize = 100;
+
 #[derive(
     Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
 )]
@@ -35,93 +59,133 @@ pub struct User {
     pub pinned_tweet_id: Option<u64>,
     pub is_sensitive: bool,
     pub is_protected: bool,
+    #[serde(default)]
+    pub flags: ProfileFlags,
+    #[serde(default)]
+    pub entities: UserEntities,
 }
 
 impl User {
     #[tracing::instrument]
     pub(crate) async fn from_result(scraper: &Scraper, result: TwtUsrResult) -> SResult<Self> {
-        if let TwtUsrResult::User(user) = result {
-            if user.rest_id.is_empty() || user.rest_id == "0" {
-                return Err(TwitterBadRestId("user", user.rest_id));
-            }
+        UserLookup::from_result(scraper, result).await?.into_user()
+    }
 
-            let website = {
-                let redirect = scraper
-                    .api_req_raw_request(scraper.make_get_req(user.legacy.url))
-                    .await?;
-                as_option!(redirect.url().to_string(), "")
-            };
+    async fn from_available(scraper: &Scraper, user: AvailableUser) -> SResult<Self> {
+        if user.rest_id.is_empty() || user.rest_id == "0" {
+            return Err(TwitterBadRestId("user", user.rest_id));
+        }
 
-            let joined = DateTime::<Utc>::from(
-                DateTime::parse_from_str(&user.legacy.created, JOINDATE_PARSE_STR)
-                    .map_err(|why| TwitterBadTimeParse(why.to_string()))?,
-            );
+        let expanded = user
+            .legacy
+            .entities
+            .url
+            .urls
+            .first()
+            .map(|url| url.expanded_url.clone())
+            .filter(|url| !url.is_empty())
+            .or_else(|| as_option!(user.legacy.url.clone(), ""));
+        // a link that won't resolve costs the website, not the user
+        let website = match scraper.website_resolution() {
+            WebsiteResolution::Eager => match resolve_link(scraper, &user.legacy.url).await {
+                Ok(resolved) => resolved.or(expanded),
+                Err(why) => {
+                    warn!(error = %why, id = user.rest_id, "Failed to resolve user website.");
+                    expanded
+                }
+            },
+            WebsiteResolution::Never | WebsiteResolution::Lazy => expanded,
+        };
 
-            let birthday = match user.legacy_extended_profile {
-                Some(lep) => lep.birthdate,
-                None => None,
-            };
+        let joined = DateTime::<Utc>::from(
+            DateTime::parse_from_str(&user.legacy.created, JOINDATE_PARSE_STR)
+                .map_err(|why| TwitterBadTimeParse(why.to_string()))?,
+        );
 
-            let pinned = {
-                if user.legacy.pinned_tweet_ids_str.is_empty() {
-                    None
-                } else {
-                    user.legacy.pinned_tweet_ids_str[0].parse::<u64>().ok()
-                }
-            };
+        let birthday = match user.legacy_extended_profile {
+            Some(lep) => lep.birthdate,
+            None => None,
+        };
 
-            let affiliation = match user.affiliates_highlighted_label {
-                Some(affiliate) => Some(UserAffiliation {
-                    badge: affiliate.label.badge.url,
-                    url: affiliate.label.url.url,
-                    description: affiliate.label.description,
-                }),
-                None => None,
-            };
+        let pinned = {
+            if user.legacy.pinned_tweet_ids_str.is_empty() {
+                None
+            } else {
+                user.legacy.pinned_tweet_ids_str[0].parse::<u64>().ok()
+            }
+        };
 
-            return Ok(Self {
-                id: user.rest_id.parse()?,
-                avatar: Avatar {
-                    url: user.legacy.profile_image_url_https,
-                    banner: user.legacy.profile_banner_url,
-                    is_nft: user.has_nft_avatar,
-                },
-                name: ProfileName {
-                    display: user.legacy.screen_name,
-                    handle: user.legacy.name,
-                },
-                profile_stats: ProfileStats {
-                    tweets: user.legacy.statuses_count,
-                    following: user.legacy.friends_count,
-                    followers: user.legacy.followers_count,
-                    likes: user.legacy.favourites_count,
-                    media_tweets: user.legacy.media_count,
-                    verified: user.legacy.verified,
-                    blue_verified: user.is_blue_verified,
-                },
-                additional_info: ProfileAdditionalInfo {
-                    affiliation,
-                    profession: user.professional,
-                    location: as_option!(user.legacy.location, "", "0"),
-                    website,
-                    joined,
-                    birthday,
-                },
-                bio: user.legacy.description,
-                pinned_tweet_id: pinned,
-                is_sensitive: user.legacy.possibly_sensitive,
-                is_protected: user.legacy.protected,
-            });
-        }
+        let affiliation = match user.affiliates_highlighted_label {
+            Some(affiliate) => Some(UserAffiliation {
+                badge: affiliate.label.badge.url,
+                url: affiliate.label.url.url,
+                description: affiliate.label.description,
+            }),
+            None => None,
+        };
+
+        let mut entities = user.legacy.entities;
+        entities.scan_bio(&user.legacy.description);
 
-        Err(UserResultError)
+        Ok(Self {
+            id: user.rest_id.parse()?,
+            avatar: Avatar {
+                url: user.legacy.profile_image_url_https,
+                banner: user.legacy.profile_banner_url,
+                is_nft: user.has_nft_avatar,
+            },
+            name: ProfileName {
+                display: user.legacy.name,
+                handle: user.legacy.screen_name,
+            },
+            profile_stats: ProfileStats {
+                tweets: user.legacy.statuses_count,
+                following: user.legacy.friends_count,
+                followers: user.legacy.followers_count,
+                likes: user.legacy.favourites_count,
+                media_tweets: user.legacy.media_count,
+                verified: user.legacy.verified,
+                blue_verified: user.is_blue_verified,
+                listed: user.legacy.listed_count,
+                normal_followers: user.legacy.normal_followers_count,
+            },
+            additional_info: ProfileAdditionalInfo {
+                affiliation,
+                profession: user.professional,
+                location: as_option!(user.legacy.location, "", "0"),
+                website,
+                joined,
+                birthday,
+                withheld_in: user.legacy.withheld_in_countries,
+            },
+            bio: user.legacy.description,
+            pinned_tweet_id: pinned,
+            is_sensitive: user.legacy.possibly_sensitive,
+            is_protected: user.legacy.protected,
+            flags: ProfileFlags {
+                default_profile: user.legacy.default_profile,
+                default_profile_image: user.legacy.default_profile_image,
+                has_custom_timelines: user.legacy.has_custom_timelines,
+                is_translator: user.legacy.is_translator,
+                is_profile_translatable: user.is_profile_translatable,
+                super_follow_eligible: user.super_follow_eligible,
+                interstitial: as_option!(user.legacy.profile_interstitial_type, ""),
+            },
+            entities,
+        })
+    }
+
+    #[tracing::instrument]
+    pub async fn new(scraper: &Scraper, handle: impl AsRef<str> + Debug) -> SResult<Self> {
+        Self::lookup(scraper, handle).await?.into_user()
     }
 
+    // like `new`, but suspended and other unavailable accounts are a result, not an error
     #[tracing::instrument]
-    pub async fn new(scraper: &Scraper, handle: impl AsRef<str>) -> SResult<Self> {
+    pub async fn lookup(scraper: &Scraper, handle: impl AsRef<str> + Debug) -> SResult<UserLookup> {
         let req = scraper
             .api_req::<UserRequest>(
-                scraper.make_get_req(twitter_request_url_handle(handle.as_ref())),
+                scraper.make_get_req(twitter_request_url_handle(scraper, handle.as_ref())),
             )
             .await?;
         // check for errors
@@ -131,8 +195,272 @@ impl User {
             }
         }
 
-        Self::from_result(scraper, req.data.user.result).await
+        UserLookup::from_result(scraper, req.data.user.result).await
+    }
+
+    // Follows a website twitter didn't expand, only under `WebsiteResolution::Lazy`.
+    #[tracing::instrument]
+    pub async fn resolve_website(&mut self, scraper: &Scraper) -> SResult<Option<&str>> {
+        let website = &mut self.additional_info.website;
+        if scraper.website_resolution() == WebsiteResolution::Lazy {
+            if let Some(link) = website.clone().filter(|link| is_short_link(link)) {
+                *website = resolve_link(scraper, &link).await?;
+            }
+        }
+        Ok(website.as_deref())
+    }
+
+    #[tracing::instrument]
+    pub async fn by_id(scraper: &Scraper, id: u64) -> SResult<Self> {
+        Self::lookup_by_id(scraper, id).await?.into_user()
+    }
+
+    #[tracing::instrument]
+    pub async fn lookup_by_id(scraper: &Scraper, id: u64) -> SResult<UserLookup> {
+        let req = scraper
+            .api_req::<UserRequest>(scraper.make_get_req(twitter_request_url_rest_id(scraper, id)))
+            .await?;
+        if let Some(why) = req.errors.first() {
+            if why.code != TWITTER_IGNORE_ERROR_CODE {
+                return Err(TwitterJSONError(why.code, why.message.clone()));
+            }
+        }
+
+        UserLookup::from_result(scraper, req.data.user.result).await
+    }
+
+    // One UsersByRestIds request per 100 ids. Suspended, deactivated and unknown ids are
+    // reported in `unavailable` rather than failing the batch, only a failed request does.
+    #[tracing::instrument(skip(ids))]
+    pub async fn by_ids(
+        scraper: &Scraper,
+        ids: impl IntoIterator<Item = u64>,
+    ) -> SResult<UserBatch> {
+        let mut seen = HashSet::default();
+        let ids = ids
+            .into_iter()
+            .filter(|id| seen.insert(*id))
+            .collect::<Vec<_>>();
+        let mut batch = UserBatch::default();
+
+        for chunk in ids.chunks(USERS_BY_REST_IDS_MAX) {
+            let req = scraper
+                .api_req::<UsersRequest>(
+                    scraper.make_get_req(twitter_request_url_rest_ids(scraper, chunk)),
+                )
+                .await?;
+            if let Some(why) = req.errors.first() {
+                if why.code != TWITTER_IGNORE_ERROR_CODE {
+                    return Err(TwitterJSONError(why.code, why.message.clone()));
+                }
+            }
+
+            // one slot per id asked for, in order, `{}` when twitter has never heard of it
+            let in_order = req.data.users.len() == chunk.len();
+            let mut found = HashSet::default();
+            for (n, slot) in req.data.users.into_iter().enumerate() {
+                let asked = in_order.then(|| chunk[n]);
+                let id = match &slot.result {
+                    Some(TwtUsrResult::User(user)) => user.rest_id.parse::<u64>().ok(),
+                    _ => asked,
+                };
+                let lookup = match slot.result {
+                    Some(result) => UserLookup::from_result(scraper, result).await,
+                    None => Ok(UserLookup::Unavailable(Unavailability::not_found())),
+                };
+
+                match lookup {
+                    Ok(
+                        UserLookup::Available(user)
+                        | UserLookup::Protected(user)
+                        | UserLookup::Withheld { user, .. },
+                    ) => {
+                        found.insert(user.id);
+                        batch.users.push(user);
+                    }
+                    Ok(lookup) => {
+                        // out of order unavailable users don't say which id they are
+                        if let (Some(id), Some(why)) = (id, lookup.unavailability()) {
+                            found.insert(id);
+                            batch.unavailable.push((id, why.clone()));
+                        }
+                    }
+                    Err(why) => {
+                        warn!(error = %why, id, "Failed to parse user.");
+                        if let Some(id) = id {
+                            found.insert(id);
+                            batch.unavailable.push((id, Unavailability::failed(&why)));
+                        }
+                    }
+                }
+            }
+
+            batch.unavailable.extend(
+                chunk
+                    .iter()
+                    .filter(|id| !found.contains(*id))
+                    .map(|id| (*id, Unavailability::not_found())),
+            );
+        }
+
+        Ok(batch)
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserBatch {
+    pub users: Vec<User>,
+    // id and why it couldn't be fetched
+    pub unavailable: Vec<(u64, Unavailability)>,
+}
+
+// What looking a user up found. Only the first three have a profile.
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub enum UserLookup {
+    Available(User),
+    // only followers see the tweets, the profile is still public
+    Protected(User),
+    // hidden in `countries`, twitter still sends the profile
+    Withheld { user: User, countries: Vec<String> },
+    Suspended(Unavailability),
+    Deactivated(Unavailability),
+    // any other reason twitter gives
+    Unavailable(Unavailability),
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct Unavailability {
+    // twitter's code for it, e.g. `Suspended`
+    pub reason: String,
+    // the text twitter shows in place of the profile
+    pub message: String,
+}
+
+impl Unavailability {
+    // an id twitter sent nothing back for
+    pub fn not_found() -> Self {
+        Unavailability {
+            reason: "NotFound".to_string(),
+            message: "No user with this id".to_string(),
+        }
+    }
+
+    // a profile came back but couldn't be parsed
+    pub fn failed(why: &TwtScrapeError) -> Self {
+        Unavailability {
+            reason: "ParseFailed".to_string(),
+            message: why.to_string(),
+        }
+    }
+}
+
+impl UserLookup {
+    #[tracing::instrument]
+    pub(crate) async fn from_result(scraper: &Scraper, result: TwtUsrResult) -> SResult<Self> {
+        match result {
+            TwtUsrResult::User(user) => {
+                let countries = user.legacy.withheld_in_countries.clone();
+                let user = User::from_available(scraper, *user).await?;
+                Ok(if !countries.is_empty() {
+                    UserLookup::Withheld { user, countries }
+                } else if user.is_protected {
+                    UserLookup::Protected(user)
+                } else {
+                    UserLookup::Available(user)
+                })
+            }
+            TwtUsrResult::UserUnavailable(unavailable) => {
+                let unavailability = Unavailability {
+                    reason: unavailable.reason,
+                    message: unavailable.unavailable_message.text,
+                };
+                Ok(match unavailability.reason.as_str() {
+                    "Suspended" => UserLookup::Suspended(unavailability),
+                    "Deactivated" => UserLookup::Deactivated(unavailability),
+                    _ => UserLookup::Unavailable(unavailability),
+                })
+            }
+        }
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        match self {
+            UserLookup::Available(user)
+            | UserLookup::Protected(user)
+            | UserLookup::Withheld { user, .. } => Some(user),
+            _ => None,
+        }
+    }
+
+    pub fn unavailability(&self) -> Option<&Unavailability> {
+        match self {
+            UserLookup::Suspended(unavailability)
+            | UserLookup::Deactivated(unavailability)
+            | UserLookup::Unavailable(unavailability) => Some(unavailability),
+            _ => None,
+        }
     }
+
+    pub fn into_user(self) -> SResult<User> {
+        match self {
+            UserLookup::Available(user)
+            | UserLookup::Protected(user)
+            | UserLookup::Withheld { user, .. } => Ok(user),
+            UserLookup::Suspended(unavailability)
+            | UserLookup::Deactivated(unavailability)
+            | UserLookup::Unavailable(unavailability) => {
+                Err(TwtScrapeError::UserUnavailable(unavailability.reason))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "scrape")]
+fn is_short_link(link: &str) -> bool {
+    link.starts_with("https://t.co/") || link.starts_with("http://t.co/")
+}
+
+// a plain page load, so no api rate limit is spent on it
+#[cfg(feature = "scrape")]
+async fn resolve_link(scraper: &Scraper, link: &str) -> SResult<Option<String>> {
+    if link.is_empty() {
+        return Ok(None);
+    }
+    let redirect = scraper.fetch_page(link).await?;
+    Ok(as_option!(redirect.url, ""))
 }
 
 impl std::hash::Hash for User {
@@ -196,6 +524,38 @@ pub struct ProfileStats {
     pub media_tweets: u32,
     pub verified: bool,
     pub blue_verified: bool,
+    // lists the user is on
+    #[serde(default)]
+    pub listed: u32,
+    // followers without the ones twitter thinks are spam
+    #[serde(default)]
+    pub normal_followers: u32,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct ProfileFlags {
+    // never changed the theme or banner
+    pub default_profile: bool,
+    // still the egg
+    pub default_profile_image: bool,
+    pub has_custom_timelines: bool,
+    pub is_translator: bool,
+    pub is_profile_translatable: bool,
+    pub super_follow_eligible: bool,
+    // the warning shown before the profile loads, e.g. `sensitive_media`
+    pub interstitial: Option<String>,
 }
 
 #[derive(
@@ -217,6 +577,9 @@ pub struct ProfileAdditionalInfo {
     pub website: Option<String>,
     pub joined: DateTime<Utc>,
     pub birthday: Option<Birthday>,
+    // country codes the profile is hidden in
+    #[serde(default)]
+    pub withheld_in: Vec<String>,
 }
 
 #[derive(
@@ -250,6 +613,7 @@ pub struct UserAffiliation {
     rkyv::Deserialize,
 )]
 pub(crate) struct UserRequest {
+    #[serde(default)]
     pub errors: Vec<Error>,
     pub data: Data,
 }
@@ -287,6 +651,59 @@ pub(crate) struct Data {
     pub user: Usr,
 }
 
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub(crate) struct UsersRequest {
+    #[serde(default)]
+    pub errors: Vec<Error>,
+    pub data: UsersData,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub(crate) struct UsersData {
+    #[serde(default)]
+    pub users: Vec<MaybeUsr>,
+}
+
+// ids twitter knows nothing about come back as `{}`
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub(crate) struct MaybeUsr {
+    #[serde(default)]
+    pub result: Option<TwtUsrResult>,
+}
+
 #[derive(
     Clone,
     Debug,
@@ -323,6 +740,16 @@ pub(crate) enum TwtUsrResult {
     UserUnavailable(Box<UserUnavailable>),
 }
 
+impl TwtUsrResult {
+    // names the user in error reports, unavailable users only have their timeline entry id
+    pub(crate) fn rest_id(&self) -> Option<&str> {
+        match self {
+            TwtUsrResult::User(user) => Some(&user.rest_id),
+            TwtUsrResult::UserUnavailable(_) => None,
+        }
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -466,6 +893,7 @@ pub struct ProfessionalCategory {
     rkyv::Deserialize,
 )]
 pub(crate) struct UserLegacy {
+    #[serde(rename = "created_at")]
     pub created: String,
     pub default_profile: bool,
     pub default_profile_image: bool,
@@ -491,6 +919,187 @@ pub(crate) struct UserLegacy {
     pub url: String,
     pub verified: bool,
     pub withheld_in_countries: Vec<String>,
+    #[serde(default)]
+    pub entities: UserEntities,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UserEntities {
+    // links, mentions and hashtags in the bio
+    #[serde(default)]
+    pub description: TextEntities,
+    // the profile's website link
+    #[serde(default)]
+    pub url: TextEntities,
+}
+
+impl UserEntities {
+    // twitter often sends only the bio's links
+    pub(crate) fn scan_bio(&mut self, bio: &str) {
+        let description = &mut self.description;
+        if description.user_mentions.is_empty() && description.hashtags.is_empty() {
+            let scanned = TextEntities::scan(bio);
+            description.user_mentions = scanned.user_mentions;
+            description.hashtags = scanned.hashtags;
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct TextEntities {
+    #[serde(default)]
+    pub urls: Vec<UrlEntity>,
+    #[serde(default)]
+    pub user_mentions: Vec<MentionEntity>,
+    #[serde(default)]
+    pub hashtags: Vec<HashtagEntity>,
+}
+
+impl TextEntities {
+    // Mentions and hashtags found in `text`, for when twitter only sent the links.
+    // `indices` count chars, like twitter's own. Links are skipped whole, so the `#` of
+    // a fragment isn't taken for a hashtag.
+    pub fn scan(text: &str) -> Self {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut entities = TextEntities::default();
+
+        let mut at = 0;
+        while at < chars.len() {
+            let starts_word = at == 0 || chars[at - 1].is_whitespace();
+            let rest = chars[at..].iter().take(8).collect::<String>();
+            if starts_word && (rest.starts_with("http://") || rest.starts_with("https://")) {
+                at = chars[at..]
+                    .iter()
+                    .position(|c| c.is_whitespace())
+                    .map_or(chars.len(), |len| at + len);
+                continue;
+            }
+
+            let sigil = chars[at];
+            if !matches!(sigil, '@' | '#') || (at > 0 && is_word(chars[at - 1])) {
+                at += 1;
+                continue;
+            }
+
+            let end = chars[at + 1..]
+                .iter()
+                .position(|c| !is_word(*c))
+                .map_or(chars.len(), |len| at + 1 + len);
+            let word = chars[at + 1..end].iter().collect::<String>();
+            let indices = [at as u32, end as u32];
+            match sigil {
+                '@' if (1..=15).contains(&word.len())
+                    && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+                {
+                    entities.user_mentions.push(MentionEntity {
+                        screen_name: word,
+                        id_str: String::new(),
+                        indices,
+                    })
+                }
+                // `#1` is not a hashtag
+                '#' if word.chars().any(|c| !c.is_numeric()) => {
+                    entities.hashtags.push(HashtagEntity {
+                        text: word,
+                        indices,
+                    })
+                }
+                _ => {}
+            }
+            at = end.max(at + 1);
+        }
+
+        entities
+    }
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct UrlEntity {
+    // the t.co link
+    pub url: String,
+    #[serde(default)]
+    pub expanded_url: String,
+    #[serde(default)]
+    pub display_url: String,
+    #[serde(default)]
+    pub indices: [u32; 2],
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct MentionEntity {
+    pub screen_name: String,
+    #[serde(default)]
+    pub id_str: String,
+    #[serde(default)]
+    pub indices: [u32; 2],
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+pub struct HashtagEntity {
+    pub text: String,
+    #[serde(default)]
+    pub indices: [u32; 2],
 }
 
 #[derive(
@@ -539,13 +1148,16 @@ pub struct Birthday {
     rkyv::Deserialize,
 )]
 pub(crate) struct UserUnavailable {
+    #[serde(default)]
     pub unavailable_message: UnavailableMessage,
+    #[serde(default)]
     pub reason: String,
 }
 
 #[derive(
     Clone,
     Debug,
+    Default,
     Hash,
     PartialEq,
     Eq,
@@ -559,3 +1171,346 @@ pub(crate) struct UnavailableMessage {
     pub rtl: bool,
     pub text: String,
 }
+
+#[cfg(feature = "scrape")]
+#[test]
+fn parse_user_names() {
+    use crate::scrape::transport::{block_on, fixture_scraper, ApiResponse, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    "https://twitter.com/i/api/graphql/ptQPCD7NrFS_TW71Lq07nw/UserByScreenName",
+                    include_str!("../fixtures/user/user_by_screen_name.json"),
+                )
+                .with_response(
+                    "https://t.co/Zq9aB1cD2e",
+                    ApiResponse::new("https://crabby.example.com/".to_string(), 200, ""),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // `screen_name` is the @handle, `name` the free form display name
+        let user = User::new(&scraper, "crabbyrust").await.unwrap();
+        assert_eq!(user.id, 3308337);
+        assert_eq!(user.name.handle, "crabbyrust");
+        assert_eq!(user.name.display, "Crabby");
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn look_up_users_by_id() {
+    use crate::scrape::transport::{block_on, fixture_scraper, ApiResponse, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    "https://twitter.com/i/api/graphql/GazOglcBvgLigl3ywt6b3Q/UserByRestId",
+                    include_str!("../fixtures/user/user_by_rest_id.json"),
+                )
+                .with_json(
+                    "https://twitter.com/i/api/graphql/OJBgJQIrij6e3cjqQ3Zu1Q/UsersByRestIds",
+                    include_str!("../fixtures/user/users_by_rest_ids.json"),
+                )
+                .with_response(
+                    "https://t.co/Zq9aB1cD2e",
+                    ApiResponse::new("https://crabby.example.com/".to_string(), 200, ""),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // twitter didn't expand this one, so it stays a t.co link until asked for
+        let mut user = User::by_id(&scraper, 3308337).await.unwrap();
+        assert_eq!(user.name.handle, "crabbyrust");
+        assert_eq!(
+            user.additional_info.website.as_deref(),
+            Some("https://t.co/Zq9aB1cD2e")
+        );
+        assert_eq!(
+            user.resolve_website(&scraper).await.unwrap(),
+            Some("https://crabby.example.com/")
+        );
+        assert_eq!(user.profile_stats.listed, 3);
+        assert!(user.flags.default_profile);
+        assert_eq!(user.flags.interstitial, None);
+
+        let bio = &user.entities.description;
+        let mentions = bio
+            .user_mentions
+            .iter()
+            .map(|mention| (mention.screen_name.as_str(), mention.indices))
+            .collect::<Vec<_>>();
+        assert_eq!(mentions, vec![("crab_corp", [20, 30])]);
+        let hashtags = bio
+            .hashtags
+            .iter()
+            .map(|hashtag| (hashtag.text.as_str(), hashtag.indices))
+            .collect::<Vec<_>>();
+        assert_eq!(hashtags, vec![("rustlang", [7, 16]), ("café", [51, 56])]);
+
+        let batch = User::by_ids(&scraper, [3308337, 404, 3308337, 405])
+            .await
+            .unwrap();
+        assert_eq!(batch.users.len(), 1);
+        assert_eq!(
+            batch.users[0].additional_info.website.as_deref(),
+            Some("https://crabby.example.com")
+        );
+        assert_eq!(
+            batch.unavailable,
+            vec![
+                (404, Unavailability::not_found()),
+                (
+                    405,
+                    Unavailability {
+                        reason: "Suspended".to_string(),
+                        message: "Account suspended".to_string(),
+                    }
+                ),
+            ]
+        );
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn resolve_websites_only_when_asked() {
+    use crate::scrape::transport::{block_on, fixture_scraper_from, ApiResponse, FixtureTransport};
+    use crate::scrape::ScraperBuilder;
+    use std::sync::Arc;
+
+    block_on(async {
+        let short = "https://t.co/Zq9aB1cD2e";
+        for resolution in [
+            WebsiteResolution::Never,
+            WebsiteResolution::Lazy,
+            WebsiteResolution::Eager,
+        ] {
+            let transport = Arc::new(
+                FixtureTransport::new()
+                    .with_json(
+                        "https://twitter.com/i/api/graphql/GazOglcBvgLigl3ywt6b3Q/UserByRestId",
+                        include_str!("../fixtures/user/user_by_rest_id.json"),
+                    )
+                    .with_response(
+                        short,
+                        ApiResponse::new("https://crabby.example.com/", 200, ""),
+                    ),
+            );
+            let builder = ScraperBuilder::new().with_website_resolution(resolution);
+            let scraper = fixture_scraper_from(builder, &transport).await;
+
+            let mut user = User::by_id(&scraper, 3308337).await.unwrap();
+            let built = transport.request_count(short);
+            let website = user
+                .resolve_website(&scraper)
+                .await
+                .unwrap()
+                .map(str::to_string);
+            // a resolved website isn't followed a second time
+            user.resolve_website(&scraper).await.unwrap();
+
+            let (built_expected, total_expected, website_expected) = match resolution {
+                WebsiteResolution::Never => (0, 0, short),
+                WebsiteResolution::Lazy => (0, 1, "https://crabby.example.com/"),
+                WebsiteResolution::Eager => (1, 1, "https://crabby.example.com/"),
+            };
+            assert_eq!(built, built_expected, "{resolution:?}");
+            assert_eq!(
+                transport.request_count(short),
+                total_expected,
+                "{resolution:?}"
+            );
+            assert_eq!(website.as_deref(), Some(website_expected), "{resolution:?}");
+        }
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn eager_website_falls_back_when_unresolved() {
+    use crate::scrape::transport::{block_on, fixture_scraper_from, ApiResponse, FixtureTransport};
+    use crate::scrape::ScraperBuilder;
+    use std::sync::Arc;
+
+    block_on(async {
+        let short = "https://t.co/Zq9aB1cD2e";
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    "https://twitter.com/i/api/graphql/GazOglcBvgLigl3ywt6b3Q/UserByRestId",
+                    include_str!("../fixtures/user/user_by_rest_id.json"),
+                )
+                .with_response(short, ApiResponse::new(short, 500, "")),
+        );
+        let builder = ScraperBuilder::new().with_website_resolution(WebsiteResolution::Eager);
+        let scraper = fixture_scraper_from(builder, &transport).await;
+
+        let user = User::by_id(&scraper, 3308337).await.unwrap();
+        assert_eq!(transport.request_count(short), 1);
+        assert_eq!(user.additional_info.website.as_deref(), Some(short));
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn parse_profile_flags_and_entities() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let mut body: serde_json::Value =
+            serde_json::from_str(include_str!("../fixtures/user/user_by_rest_id.json")).unwrap();
+        let user = &mut body["data"]["user"]["result"];
+        user["super_follow_eligible"] = true.into();
+        let legacy = &mut user["legacy"];
+        legacy["default_profile"] = false.into();
+        legacy["has_custom_timelines"] = true.into();
+        legacy["profile_interstitial_type"] = "sensitive_media".into();
+        legacy["withheld_in_countries"] = json!(["DE", "FR"]);
+        // twitter's own entities are kept, the bio isn't scanned on top of them
+        legacy["entities"]["description"] = json!({
+            "user_mentions": [{"screen_name": "crab_corp", "id_str": "42", "indices": [20, 30]}],
+        });
+        legacy["entities"]["url"]["urls"] = json!([{
+            "url": "https://t.co/Zq9aB1cD2e",
+            "expanded_url": "https://crabby.example.com",
+            "display_url": "crabby.example.com",
+            "indices": [0, 23],
+        }]);
+
+        let transport = Arc::new(FixtureTransport::new().with_json(
+            "https://twitter.com/i/api/graphql/GazOglcBvgLigl3ywt6b3Q/UserByRestId",
+            body.to_string(),
+        ));
+        let scraper = fixture_scraper(&transport).await;
+
+        let lookup = User::lookup_by_id(&scraper, 3308337).await.unwrap();
+        let countries = vec!["DE".to_string(), "FR".to_string()];
+        let user = match lookup {
+            UserLookup::Withheld {
+                user,
+                countries: withheld,
+            } => {
+                assert_eq!(withheld, countries);
+                user
+            }
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(user.additional_info.withheld_in, countries);
+        assert_eq!(
+            user.flags,
+            ProfileFlags {
+                default_profile: false,
+                default_profile_image: false,
+                has_custom_timelines: true,
+                is_translator: false,
+                is_profile_translatable: false,
+                super_follow_eligible: true,
+                interstitial: Some("sensitive_media".to_string()),
+            }
+        );
+        assert_eq!(
+            user.entities.description,
+            TextEntities {
+                user_mentions: vec![MentionEntity {
+                    screen_name: "crab_corp".to_string(),
+                    id_str: "42".to_string(),
+                    indices: [20, 30],
+                }],
+                ..TextEntities::default()
+            }
+        );
+        assert_eq!(
+            user.entities.url.urls[0].expanded_url,
+            "https://crabby.example.com"
+        );
+        assert_eq!(
+            user.additional_info.website.as_deref(),
+            Some("https://crabby.example.com")
+        );
+        assert_eq!(user.profile_stats.normal_followers, 340);
+    });
+}
+
+#[cfg(feature = "scrape")]
+#[test]
+fn surface_unavailable_users() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    block_on(async {
+        let transport = Arc::new(FixtureTransport::new()
+                .with_json(
+                    "https://twitter.com/i/api/graphql/ptQPCD7NrFS_TW71Lq07nw/UserByScreenName",
+                    r#"{"data":{"user":{"result":{"__typename":"UserUnavailable","reason":"Suspended","unavailable_message":{"rtl":false,"text":"Account suspended"}}}}}"#,
+                ));
+        let scraper = fixture_scraper(&transport).await;
+
+        let lookup = User::lookup(&scraper, "gone").await.unwrap();
+        assert_eq!(
+            lookup,
+            UserLookup::Suspended(Unavailability {
+                reason: "Suspended".to_string(),
+                message: "Account suspended".to_string(),
+            })
+        );
+        assert!(lookup.user().is_none());
+        assert!(matches!(
+            User::new(&scraper, "gone").await,
+            Err(TwtScrapeError::UserUnavailable(reason)) if reason == "Suspended"
+        ));
+    });
+}
+
+#[test]
+fn scan_text_entities() {
+    let mentions = |text: &str| {
+        TextEntities::scan(text)
+            .user_mentions
+            .into_iter()
+            .map(|mention| (mention.screen_name, mention.indices))
+            .collect::<Vec<_>>()
+    };
+    let hashtags = |text: &str| {
+        TextEntities::scan(text)
+            .hashtags
+            .into_iter()
+            .map(|hashtag| (hashtag.text, hashtag.indices))
+            .collect::<Vec<_>>()
+    };
+
+    // right at the start and end of the text
+    assert_eq!(mentions("@crab"), vec![("crab".to_string(), [0, 5])]);
+    assert_eq!(hashtags("#rust"), vec![("rust".to_string(), [0, 5])]);
+    assert_eq!(
+        hashtags("#a #b"),
+        vec![("a".to_string(), [0, 2]), ("b".to_string(), [3, 5])]
+    );
+    assert_eq!(mentions("hi @crab."), vec![("crab".to_string(), [3, 8])]);
+
+    // not entities
+    assert!(mentions("@").is_empty());
+    assert!(hashtags("#").is_empty());
+    assert!(hashtags("#1 fan").is_empty());
+    assert!(hashtags("a#b").is_empty());
+    assert!(mentions("mail me@example.com").is_empty());
+    assert!(mentions("@a_handle_that_is_too_long").is_empty());
+    assert!(mentions("@crabé").is_empty());
+
+    // indices count chars, not bytes
+    assert_eq!(hashtags("café #thé"), vec![("thé".to_string(), [5, 9])]);
+
+    // links are skipped whole, at either end of the text too
+    assert!(hashtags("https://crabby.example.com/#about").is_empty());
+    assert!(mentions("see https://example.com/@crab").is_empty());
+    assert_eq!(
+        hashtags("https://example.com/#a #b http://example.com/#c"),
+        vec![("b".to_string(), [23, 25])]
+    );
+}
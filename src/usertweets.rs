@@ -1,82 +1,105 @@
 use crate::error::SResult;
 use crate::error::TwtScrapeError::TwitterJSONError;
 #[cfg(feature = "scrape")]
+use crate::scrape::endpoints::Operation;
+#[cfg(feature = "scrape")]
+use crate::scrape::pagination::{
+    collect_partial, items, paginate, Checkpoint, ItemError, Page, PartialResult,
+};
+#[cfg(feature = "scrape")]
 use crate::scrape::Scraper;
 use crate::tweet::{Cursor, Tweet, TweetEnt, TweetItemContent, TweetResults};
 use crate::user::{Error, User};
 use ahash::{HashSet, HashSetExt};
+#[cfg(feature = "scrape")]
+use futures::Stream;
 use rkyv::Archive;
-use serde::de::{MapAccess, Visitor};
 use serde::{de, Deserialize, Deserializer, Serialize};
-use std::collections::VecDeque;
-use std::fmt;
-use std::fmt::Display;
 #[cfg(feature = "scrape")]
-use tracing::{span, warn};
+use serde_json::json;
+#[cfg(feature = "scrape")]
+use tracing::warn;
 
 #[cfg(feature = "scrape")]
 pub fn twitter_request_url_user_tweet_and_replies(
+    scraper: &Scraper,
     id: u64,
     cursor: Option<impl AsRef<str>>,
 ) -> String {
-    match cursor {
-        Some(crsr) => {
-            let crsr = urlencoding::encode(crsr.as_ref());
-            format!("https://twitter.com/i/api/graphql/s0hG9oAmWEYVBqOLJP-TBQ/UserTweetsAndReplies?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A40%2C%22cursor%22%3A%22{crsr}%22%2C%22includePromotedContent%22%3Afalse%2C%22withCommunity%22%3Atrue%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%2C%22withVoice%22%3Atrue%2C%22withV2Timeline%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
-        None => {
-            format!("https://twitter.com/i/api/graphql/s0hG9oAmWEYVBqOLJP-TBQ/UserTweetsAndReplies?variables=%7B%22userId%22%3A%22{id}%22%2C%22count%22%3A40%2C%22includePromotedContent%22%3Afalse%2C%22withCommunity%22%3Atrue%2C%22withSuperFollowsUserFields%22%3Atrue%2C%22withDownvotePerspective%22%3Afalse%2C%22withReactionsMetadata%22%3Afalse%2C%22withReactionsPerspective%22%3Afalse%2C%22withSuperFollowsTweetFields%22%3Atrue%2C%22withVoice%22%3Atrue%2C%22withV2Timeline%22%3Atrue%7D&features=%7B%22responsive_web_twitter_blue_verified_badge_is_enabled%22%3Atrue%2C%22verified_phone_label_enabled%22%3Afalse%2C%22responsive_web_graphql_timeline_navigation_enabled%22%3Atrue%2C%22unified_cards_ad_metadata_container_dynamic_card_content_query_enabled%22%3Atrue%2C%22tweetypie_unmention_optimization_enabled%22%3Atrue%2C%22responsive_web_uc_gql_enabled%22%3Atrue%2C%22vibe_api_enabled%22%3Atrue%2C%22responsive_web_edit_tweet_api_enabled%22%3Atrue%2C%22graphql_is_translatable_rweb_tweet_is_translatable_enabled%22%3Atrue%2C%22standardized_nudges_misinfo%22%3Atrue%2C%22tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled%22%3Afalse%2C%22interactive_text_enabled%22%3Atrue%2C%22responsive_web_text_conversations_enabled%22%3Afalse%2C%22responsive_web_enhance_cards_enabled%22%3Atrue%7D")
-        }
+    let mut variables = json!({ "userId": id.to_string() });
+    if let Some(cursor) = cursor {
+        variables["cursor"] = cursor.as_ref().into();
     }
+    scraper.graphql_url(Operation::UserTweetsAndReplies, variables)
 }
 
-#[derive(
-    Clone,
-    Debug,
-    PartialEq,
-    Eq,
-    Hash,
-    Serialize,
-    Deserialize,
-    Archive,
-    rkyv::Serialize,
-    rkyv::Deserialize,
-)]
+// no rkyv here, it can only archive sets whose archived items hash, `TimelineThread` archives
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserTweetsAndReplies {
     pub users: HashSet<User>,
     pub tweets: HashSet<Tweet>,
 }
 
+// a tweet or conversation from the timeline, with its whole thread and the users in it
+#[derive(
+    Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
+pub struct TimelineThread {
+    pub tweets: Vec<Tweet>,
+    pub users: Vec<User>,
+}
+
 #[cfg(feature = "scrape")]
 impl UserTweetsAndReplies {
-    #[tracing::instrument]
-    pub async fn scroll_user_timeline(scraper: &Scraper, user_handle: String) -> SResult<Self> {
-        let _span_ = span!(tracing::Level::TRACE, "user_handle", user_handle).entered();
-
-        let user = User::new(scraper, &user_handle).await?;
+    pub fn stream_pages(
+        scraper: &Scraper,
+        user_id: u64,
+    ) -> impl Stream<Item = SResult<Page<TimelineThread>>> + '_ {
+        Self::stream_pages_from(scraper, user_id, Checkpoint::default())
+    }
 
-        let timeline_request_url = twitter_request_url_user_tweet_and_replies(user.id, None);
+    pub fn stream_pages_from(
+        scraper: &Scraper,
+        user_id: u64,
+        start: Checkpoint,
+    ) -> impl Stream<Item = SResult<Page<TimelineThread>>> + '_ {
+        paginate(start, move |cursor| {
+            UserTweetAndRepliesRequest::fetch_page(scraper, user_id, cursor)
+        })
+    }
 
-        let mut timelines_requests =
-            Vec::with_capacity(user.profile_stats.tweets.min(3200) as usize / 40);
+    pub fn stream(
+        scraper: &Scraper,
+        user_id: u64,
+    ) -> impl Stream<Item = SResult<TimelineThread>> + '_ {
+        items(Self::stream_pages(scraper, user_id))
+    }
 
-        let first_request = scraper
-            .api_req::<UserTweetAndRepliesRequest>(scraper.make_get_req(timeline_request_url))
-            .await?;
-        // find the cursor
-        let first_cursor = first_request.filter_cursor();
-
-        if let Some(fc) = first_cursor {
-            timelines_requests.append(
-                &mut UserTweetAndRepliesRequest::scroll(scraper, user.id, fc.to_string())
-                    .await?
-                    .into(),
-            );
-        }
+    #[tracing::instrument]
+    pub async fn scroll_user_timeline(scraper: &Scraper, user_handle: String) -> SResult<Self> {
+        Self::scroll_user_timeline_partial(scraper, user_handle, Checkpoint::default())
+            .await
+            .into_result()
+    }
 
-        // lets parse these requests
+    #[tracing::instrument]
+    pub async fn scroll_user_timeline_partial(
+        scraper: &Scraper,
+        user_handle: String,
+        start: Checkpoint,
+    ) -> PartialResult<Self> {
+        let user = match User::new(scraper, &user_handle).await {
+            Ok(user) => user,
+            Err(why) => {
+                let empty = UserTweetsAndReplies {
+                    users: HashSet::new(),
+                    tweets: HashSet::new(),
+                };
+                return PartialResult::failed(empty, start, why);
+            }
+        };
 
-        let (mut tweets, mut users) = {
+        let (tweets, users) = {
             let mut max = user.profile_stats.tweets;
             if max > 3200 {
                 // un oh terminally online tankie posting alert
@@ -88,109 +111,15 @@ impl UserTweetsAndReplies {
             )
         };
 
-        for request in timelines_requests {
-            for inst in request.data.user.result.timeline_v2.timeline.instructions {
-                if let Instruction::TimelineAddEntries(add) = inst {
-                    for entry in add.entries {
-                        match entry {
-                            Entry::HomeConversation(homeconvo) => {
-                                let first = homeconvo.content.items.first();
-                                let last = homeconvo.content.items.last();
-                                let equal = first == last;
-
-                                match (first, last) {
-                                    (Some(f), Some(l)) => {
-                                        let firstid = match &f.item.tweet_results {
-                                            TweetResults::Ok(t) => t.rest_id.clone(),
-                                            TweetResults::Tombstone(tomb) => continue,
-                                        };
-
-                                        let (mut twts, mut usrs) = match Tweet::parse_thread(
-                                            scraper, &firstid,
-                                        )
-                                        .await
-                                        {
-                                            Ok(x) => x,
-                                            Err(why) => {
-                                                warn!(
-                                                            user_handle,
-                                                            tweet = firstid,
-                                                            error = why,
-                                                            "Failed to get tweet for user timeline. Continuing."
-                                                        );
-                                                continue;
-                                            }
-                                        };
-
-                                        tweets.append(&mut twts);
-                                        users.append(&mut usrs);
-                                        if !equal {
-                                            let firstid = match &l.item.tweet_results {
-                                                TweetResults::Ok(t) => t.rest_id.clone(),
-                                                TweetResults::Tombstone(tomb) => continue,
-                                            };
-
-                                            let (mut twts, mut usrs) = match Tweet::parse_thread(
-                                                scraper, &firstid,
-                                            )
-                                            .await
-                                            {
-                                                Ok(x) => x,
-                                                Err(why) => {
-                                                    warn!(
-                                                            user_handle,
-                                                            tweet = firstid,
-                                                            error = why,
-                                                            "Failed to get tweet for user timeline. Continuing."
-                                                        );
-                                                    continue;
-                                                }
-                                            };
-
-                                            tweets.append(&mut twts);
-                                            users.append(&mut usrs);
-                                        }
-                                    }
-                                    (_, _) => {
-                                        warn!(
-                                            user_handle,
-                                            "Failed to get tweet for user timeline. Continuing."
-                                        );
-                                        continue;
-                                    }
-                                }
-                            }
-                            Entry::Tweet(tweet) => {
-                                let firstid = match &tweet.item_content.tweet_results {
-                                    TweetResults::Ok(t) => t.rest_id.clone(),
-                                    TweetResults::Tombstone(tomb) => continue,
-                                };
-
-                                let (mut twts, mut usrs) =
-                                    match Tweet::parse_thread(scraper, &firstid).await {
-                                        Ok(x) => x,
-                                        Err(why) => {
-                                            warn!(
-                                            user_handle,
-                                            tweet = firstid,
-                                            error = why,
-                                            "Failed to get tweet for user timeline. Continuing."
-                                        );
-                                            continue;
-                                        }
-                                    };
-
-                                tweets.append(&mut twts);
-                                users.append(&mut usrs);
-                            }
-                            Entry::Cursor(_) => continue,
-                        }
-                    }
-                }
+        let pages = Self::stream_pages_from(scraper, user.id, start.clone());
+        collect_partial(pages, start).await.map(|threads| {
+            let (mut tweets, mut users) = (tweets, users);
+            for thread in threads {
+                tweets.extend(thread.tweets);
+                users.extend(thread.users);
             }
-        }
-
-        Ok(UserTweetsAndReplies { users, tweets })
+            UserTweetsAndReplies { users, tweets }
+        })
     }
 }
 
@@ -207,6 +136,7 @@ impl UserTweetsAndReplies {
     rkyv::Deserialize,
 )]
 pub(crate) struct UserTweetAndRepliesRequest {
+    #[serde(default)]
     pub errors: Vec<Error>,
     pub data: UserTARData,
 }
@@ -224,7 +154,7 @@ impl UserTweetAndRepliesRequest {
 
     pub(crate) fn filter_cursor(&self) -> Option<&str> {
         for inst in &self.data.user.result.timeline_v2.timeline.instructions {
-            if let Instruction::TimelineAddEntries(add) = inst {
+            if let Instruction::AddEntries(add) = inst {
                 for entry in &add.entries {
                     if let Entry::Cursor(c) = entry {
                         if c.content.item_content.cursor_type.starts_with("Bottom") {
@@ -239,41 +169,108 @@ impl UserTweetAndRepliesRequest {
     }
 
     #[tracing::instrument]
-    pub(crate) async fn scroll(
+    pub(crate) async fn fetch_page(
         scraper: &Scraper,
-        id: u64,
-        first_cursor: String,
-    ) -> SResult<VecDeque<Self>> {
-        let mut requests = VecDeque::with_capacity(5);
-
-        let mut cursor_counter = first_cursor.to_string();
-        let mut break_on_next = false;
-        loop {
-            let scrolled_up_request = scraper
-                .api_req::<UserTweetAndRepliesRequest>(scraper.make_get_req(
-                    twitter_request_url_user_tweet_and_replies(id, Some(&cursor_counter)),
-                ))
-                .await?;
-
-            scrolled_up_request.json_request_filter_errors()?;
-
-            requests.push_front(scrolled_up_request);
-            if break_on_next {
-                break;
-            }
+        user_id: u64,
+        cursor: Option<String>,
+    ) -> SResult<Page<TimelineThread>> {
+        let request = scraper
+            .api_req::<UserTweetAndRepliesRequest>(scraper.make_get_req(
+                twitter_request_url_user_tweet_and_replies(scraper, user_id, cursor),
+            ))
+            .await?;
 
-            match scrolled_up_request.filter_cursor() {
-                Some(bottom) => {
-                    cursor_counter = bottom.to_string();
+        request.json_request_filter_errors()?;
+
+        let next_cursor = request.filter_cursor().map(str::to_string);
+        let mut threads = Vec::with_capacity(40);
+        let mut errors = vec![];
+
+        for inst in request.data.user.result.timeline_v2.timeline.instructions {
+            if let Instruction::AddEntries(add) = inst {
+                for entry in add.entries {
+                    match entry {
+                        Entry::HomeConversation(homeconvo) => {
+                            let (first, last) = match (
+                                homeconvo.content.items.first(),
+                                homeconvo.content.items.last(),
+                            ) {
+                                (Some(f), Some(l)) => (f, l),
+                                (_, _) => {
+                                    warn!(
+                                        user_id,
+                                        "Failed to get tweet for user timeline. Continuing."
+                                    );
+                                    continue;
+                                }
+                            };
+
+                            threads.extend(
+                                thread_of(scraper, user_id, &first.item.tweet_results, &mut errors)
+                                    .await,
+                            );
+                            if first != last {
+                                threads.extend(
+                                    thread_of(
+                                        scraper,
+                                        user_id,
+                                        &last.item.tweet_results,
+                                        &mut errors,
+                                    )
+                                    .await,
+                                );
+                            }
+                        }
+                        Entry::Tweet(tweet) => {
+                            threads.extend(
+                                thread_of(
+                                    scraper,
+                                    user_id,
+                                    &tweet.item_content.tweet_results,
+                                    &mut errors,
+                                )
+                                .await,
+                            );
+                        }
+                        Entry::Cursor(_) => continue,
+                    }
                 }
-                None => break_on_next = true,
             }
         }
 
-        Ok(requests)
+        Ok(Page::new(threads, next_cursor).with_errors(errors))
     }
 }
 
+// tombstones are skipped, a thread that fails part way keeps what it got
+#[cfg(feature = "scrape")]
+async fn thread_of(
+    scraper: &Scraper,
+    user_id: u64,
+    results: &TweetResults,
+    errors: &mut Vec<ItemError>,
+) -> Option<TimelineThread> {
+    let id = match results {
+        TweetResults::Ok(t) => t.rest_id.clone(),
+        TweetResults::Tombstone(_) => return None,
+    };
+
+    let thread = Tweet::parse_thread_partial(scraper, id.as_str()).await;
+    errors.extend(thread.errors);
+    if let Some(why) = thread.failure {
+        warn!(
+            user_id,
+            tweet = id,
+            error = %why,
+            "Failed to get tweet for user timeline. Continuing."
+        );
+        errors.push(ItemError::new(&id, &why));
+    }
+
+    let (tweets, users) = thread.data;
+    (!tweets.is_empty()).then_some(TimelineThread { tweets, users })
+}
+
 #[derive(
     Clone,
     Debug,
@@ -369,9 +366,12 @@ pub(crate) struct Timeline {
 )]
 #[serde(tag = "type")]
 pub(crate) enum Instruction {
-    TimelineClearCache,
-    TimelineAddEntries(TimelineAddEntry),
-    TimelinePinEntry(TimelinePinEntry),
+    #[serde(rename = "TimelineClearCache")]
+    ClearCache,
+    #[serde(rename = "TimelineAddEntries")]
+    AddEntries(TimelineAddEntry),
+    #[serde(rename = "TimelinePinEntry")]
+    PinEntry(TimelinePinEntry),
 }
 
 #[derive(
@@ -439,110 +439,45 @@ pub(crate) struct TimelineAddEntry {
     pub entries: Vec<Entry>,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[derive(
+    Clone, Debug, Hash, PartialEq, Eq, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize,
+)]
 pub(crate) enum Entry {
     HomeConversation(HomeConversation),
     Tweet(TweetEnt),
     Cursor(Cursor),
 }
 
+// The kind of entry is only told by its id. Tweets are read from the entry's `content`,
+// conversations and cursors from the whole entry.
 impl<'de> Deserialize<'de> for Entry {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        enum Field {
-            EntryId,
-            SortId,
-            Content,
+        let entry = serde_json::Value::deserialize(deserializer)?;
+        let entry_id = entry
+            .get("entryId")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| de::Error::missing_field("entryId"))?;
+
+        if entry_id.starts_with("tweet-") {
+            let content = entry
+                .get("content")
+                .cloned()
+                .ok_or_else(|| de::Error::missing_field("content"))?;
+            serde_json::from_value(content).map(Entry::Tweet)
+        } else if entry_id.starts_with("homeConversation-") {
+            serde_json::from_value(entry).map(Entry::HomeConversation)
+        } else if entry_id.starts_with("cursor-") {
+            serde_json::from_value(entry).map(Entry::Cursor)
+        } else {
+            return Err(de::Error::unknown_variant(
+                entry_id,
+                &["tweet", "homeConversation", "cursor"],
+            ));
         }
-
-        impl<'de> Deserialize<'de> for Field {
-            fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
-            where
-                D: Deserializer<'de>,
-            {
-                struct FieldVisitor;
-
-                impl<'de> Visitor<'de> for FieldVisitor {
-                    type Value = Field;
-
-                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("entry type sort content")
-                    }
-
-                    fn visit_str<E>(self, value: &str) -> Result<Field, E>
-                    where
-                        E: de::Error,
-                    {
-                        match value {
-                            "entryId" => Ok(Field::EntryId),
-                            "sortId" => Ok(Field::SortId),
-                            "content" => Ok(Field::Content),
-                            _ => Err(de::Error::unknown_field(
-                                value,
-                                &["entryId", "sortId", "content"],
-                            )),
-                        }
-                    }
-                }
-
-                deserializer.deserialize_identifier(FieldVisitor)
-            }
-        }
-
-        struct EntryVisitor;
-
-        impl<'de> Visitor<'de> for EntryVisitor {
-            type Value = Entry;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("enum Entry")
-            }
-
-            fn visit_map<V>(self, mut map: V) -> Result<Entry, V::Error>
-            where
-                V: MapAccess<'de>,
-            {
-                let mut entry_id: Option<String> = None;
-                let mut __typename: Option<String> = None;
-                let mut sort_id: Option<String> = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::EntryId => {
-                            entry_id = Some(map.next_value()?);
-                        }
-                        Field::SortId => {
-                            sort_id = Some(map.next_value()?);
-                        }
-                        Field::Content => {
-                            if let Some(entry) = &entry_id {
-                                if entry.starts_with("tweet-") {
-                                    Ok(Entry::Tweet(map.next_value()?))
-                                } else if entry.starts_with("homeConversation-") {
-                                    Ok(Entry::ConversationThread(map.next_value()?))
-                                } else if entry.starts_with("cursor-") {
-                                    Ok(Entry::Cursor(map.next_value()?))
-                                } else {
-                                    Err(de::Error::unknown_variant(
-                                        entry,
-                                        &["tweet", "homeConversation", "cursor"],
-                                    ))
-                                }
-                            }
-                            Err(de::Error::unknown_variant(
-                                "None",
-                                &["tweet", "homeConversation", "cursor"],
-                            ))
-                        }
-                    }
-                }
-                Err(de::Error::missing_field("content"))
-            }
-        }
-
-        const VARIANTS: &[&str] = &["Tweet", "homeConversation", "Cursor"];
-        deserializer.deserialize_enum("Entry", VARIANTS, EntryVisitor)
+        .map_err(de::Error::custom)
     }
 }
 
@@ -612,3 +547,80 @@ pub(crate) struct HCConversationMeta {
     pub all_tweet_ids: Vec<String>,
     pub enable_deduplication: bool,
 }
+
+#[cfg(feature = "scrape")]
+#[test]
+fn fetch_threads_of_timeline_tweets() {
+    use crate::scrape::transport::{block_on, fixture_scraper, FixtureTransport};
+    use std::sync::Arc;
+
+    let timeline = "https://twitter.com/i/api/graphql/s0hG9oAmWEYVBqOLJP-TBQ/UserTweetsAndReplies";
+    let detail = "https://twitter.com/i/api/graphql/BoHLKeBvibdYDiJON1oqTg/TweetDetail";
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    timeline,
+                    include_str!("../fixtures/usertweets/user_tweets_and_replies.json"),
+                )
+                .with_json(detail, include_str!("../fixtures/tweet/tweet_detail.json"))
+                .with_json(
+                    detail,
+                    include_str!("../fixtures/tweet/tweet_detail_replies.json"),
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // the tombstone has no thread to fetch, the tweet's thread runs over two pages
+        let page = UserTweetAndRepliesRequest::fetch_page(&scraper, 3308337, None)
+            .await
+            .unwrap();
+        assert_eq!(page.next_cursor.as_deref(), Some("timeline-2"));
+        assert!(page.errors.is_empty());
+        assert_eq!(page.items.len(), 1);
+        let ids = page.items[0]
+            .tweets
+            .iter()
+            .map(|tweet| tweet.id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                1700000000000000001,
+                1700000000000000002,
+                1700000000000000003,
+                1700000000000000004
+            ]
+        );
+        assert_eq!(page.items[0].users.len(), 1);
+        assert_eq!(page.items[0].users[0].name.handle, "crabbyrust");
+        assert_eq!(transport.request_count(detail), 2);
+    });
+
+    block_on(async {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with_json(
+                    timeline,
+                    include_str!("../fixtures/usertweets/user_tweets_and_replies.json"),
+                )
+                .with_json(
+                    detail,
+                    r#"{"errors":[{"message":"Over capacity","code":130}],"data":{}}"#,
+                ),
+        );
+        let scraper = fixture_scraper(&transport).await;
+
+        // a thread that can't be fetched is named by its tweet, the page carries on
+        let page = UserTweetAndRepliesRequest::fetch_page(&scraper, 3308337, None)
+            .await
+            .unwrap();
+        assert!(page.items.is_empty());
+        let failed = page
+            .errors
+            .iter()
+            .map(|error| error.item.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec!["1700000000000000002"]);
+    });
+}